@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::{Inventory, Player};
+use crate::{
+    tiled_map::{TiledMap, TiledProperties},
+    Inventory, Player,
+};
 
 #[derive(Component, Debug)]
 struct OnHud;
@@ -17,7 +20,7 @@ pub struct HudPlugin;
 impl Plugin for HudPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_hud)
-            .add_systems(Update, update_hud_inventory);
+            .add_systems(Update, (update_hud_inventory, update_hud_level));
     }
 }
 fn update_hud_inventory(
@@ -35,6 +38,23 @@ fn update_hud_inventory(
     }
 }
 
+/// Show the `title` property of the map, once it is spawned
+fn update_hud_level(
+    map_query: Query<&TiledProperties, (With<Handle<TiledMap>>, Changed<TiledProperties>)>,
+    mut query: Query<&mut Text, With<OnLevel>>,
+) {
+    let Some(title) = map_query
+        .iter()
+        .find_map(|properties| properties.get_string("title"))
+    else {
+        return;
+    };
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = title.to_string();
+    }
+}
+
 fn setup_hud(mut commands: Commands) {
     let level_text = "Level 1";
     let inventory_text = "";
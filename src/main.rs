@@ -1,12 +1,14 @@
 use std::fmt;
 
-use bevy::{log, prelude::*, window::WindowResolution};
+use bevy::{prelude::*, transform::TransformSystem, window::WindowResolution};
 use bevy_inspector_egui::{quick::WorldInspectorPlugin, InspectorOptions};
 use bevy_simple_tilemap::prelude::*;
 use hud::HudPlugin;
 use movement::MovementPlugin;
+use serde::Deserialize;
 use tiled_map::{
-    TiledMap, TiledMapBundle, TiledMapPlugin, TiledObject, TiledShape, TilemapTileSize,
+    TiledClassAppExt, TiledLoaderSettings, TiledMap, TiledMapBundle, TiledMapEvent, TiledMapPlugin,
+    TiledReloadAppExt, TiledShape, TilemapTileSize,
 };
 
 use crate::movement::Moveable;
@@ -37,10 +39,23 @@ fn main() {
         .add_plugins(MovementPlugin)
         .add_plugins(HudPlugin)
         .add_systems(Startup, setup)
+        .add_systems(Update, log_map_events)
         .add_systems(
             PostUpdate,
-            (setup_player, setup_portals, setup_collectables),
+            log_spawned_shapes.after(TransformSystem::TransformPropagate),
         )
+        // Objects are given their components from their Tiled class and custom properties
+        .register_tiled_class::<Player>("Player")
+        .register_tiled_class::<Inventory>("Player")
+        .register_tiled_class::<Moveable>("Player")
+        .register_tiled_class::<Portal>("Portal")
+        .register_tiled_class::<Inventory>("Portal")
+        .register_tiled_class::<Collectable<Potion>>("Potion")
+        .register_tiled_class::<Collectable<Weapon>>("Weapon")
+        // Keep the player's state when the map is edited while the game is running
+        .preserve_on_tiled_reload::<Player>()
+        .preserve_on_tiled_reload::<Inventory>()
+        .preserve_on_tiled_reload::<Moveable>()
         .add_plugins(WorldInspectorPlugin::new())
         // Debugging
         .register_type::<Player>()
@@ -52,166 +67,93 @@ fn main() {
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2dBundle::default());
 
-    let map_handle: Handle<TiledMap> = asset_server.load("level1.tmx");
+    let map_handle: Handle<TiledMap> =
+        asset_server.load_with_settings("level1.tmx", |settings: &mut TiledLoaderSettings| {
+            settings.scale = 3.0;
+            settings.project = Some("tiles.tiled-project".to_string());
+        });
 
     // TODO: If the tiled_map is spawned here... will all the other objects and sprites be spawned
     // even if this command below isn't executed!?
-    commands.spawn(TiledMapBundle {
-        tiled_map: map_handle,
-        ..Default::default()
-    });
+    commands
+        .spawn(TiledMapBundle {
+            tiled_map: map_handle,
+            ..Default::default()
+        })
+        .insert(Name::new("Level 1"));
 }
 
-fn setup_player(
-    mut commands: Commands,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
-    tiled_object_query: Query<(Entity, &TiledObject)>,
-) {
-    // Check to see if the maps were updated, if so continue to build objects else return
-    if new_maps.is_empty() {
-        return;
-    }
+fn log_map_events(mut events: EventReader<TiledMapEvent>, names: Query<&Name>) {
+    for event in events.read() {
+        let (action, entity, handle) = match event {
+            TiledMapEvent::Loaded { entity, handle } => ("Loaded", entity, handle),
+            TiledMapEvent::Spawned { entity, handle } => ("Spawned", entity, handle),
+            TiledMapEvent::Despawned { entity, handle } => ("Despawned", entity, handle),
+            TiledMapEvent::Reloaded { entity, handle } => ("Reloaded", entity, handle),
+        };
 
-    for (entity, tiled_object) in tiled_object_query.iter() {
-        match &tiled_object.class {
-            Some(class) => {
-                if class == "Player" {
-                    commands
-                        .entity(entity)
-                        .insert(Player)
-                        .insert(Inventory::default())
-                        .insert(Moveable::new());
-                }
-            }
-            _ => {
-                commands.entity(entity);
-            }
+        let name = names.get(*entity).map_or("map", |name| name.as_str());
+        match handle.path() {
+            Some(path) => info!("{action} {name} from {path}"),
+            None => info!("{action} {name}"),
         }
     }
-
-    log::info!("Setup player complete.");
 }
 
-fn setup_portals(
-    mut commands: Commands,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
-    tiled_shape_query: Query<(Entity, &TiledShape), With<TiledShape>>,
+/// Where the map's named shapes, such as spawn points and trigger areas, end up in the world
+fn log_spawned_shapes(
+    shapes: Query<(Entity, &TiledShape), Added<TiledShape>>,
+    parents: Query<&Parent>,
+    maps: Query<&GlobalTransform, With<Handle<TiledMap>>>,
 ) {
-    // Check to see if the maps were updated, if so continue to build objects else return
-    if new_maps.is_empty() {
-        return;
-    }
-
-    for (entity, tiled_shape) in tiled_shape_query.iter() {
-        match &tiled_shape.class {
-            Some(class) => {
-                if class != "Portal" {
-                    return;
-                }
-
-                let mut c = commands.entity(entity);
-                c.insert(Portal).insert(Name::new(class.clone()));
-
-                if let Some(name) = &tiled_shape.name {
-                    let mut inventory = Inventory::new();
-
-                    for n in name.split(',') {
-                        match n {
-                            "Green potion" => {
-                                inventory.potion = Some(Collectable(Potion::Green));
-                            }
-                            "Red potion" => {
-                                inventory.potion = Some(Collectable(Potion::Red));
-                            }
-                            "Blue potion" => {
-                                inventory.potion = Some(Collectable(Potion::Blue));
-                            }
-                            "Hammer" => {
-                                inventory.weapon = Some(Collectable(Weapon::Hammer));
-                            }
-                            "Axe" => {
-                                inventory.weapon = Some(Collectable(Weapon::Axe));
-                            }
-                            "Sword" => {
-                                inventory.weapon = Some(Collectable(Weapon::Sword));
-                            }
-                            _ => (),
-                        }
-                    }
-
-                    c.insert(inventory);
-                }
-            }
-            _ => {
-                commands.entity(entity);
-            }
+    for (entity, shape) in &shapes {
+        let Some(name) = &shape.name else {
+            continue;
         };
-    }
-
-    log::info!("Setup portal complete.");
-}
-
-fn setup_collectables(
-    mut commands: Commands,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
-    tiled_object_query: Query<(Entity, &TiledObject)>,
-) {
-    // Check to see if the maps were updated, if so continue to build objects else return
-    if new_maps.is_empty() {
-        return;
-    }
-
-    for (entity, tiled_object) in tiled_object_query.iter() {
-        match &tiled_object.class {
-            Some(class) => {
-                if class == "Collectable" {
-                    let mut c = commands.entity(entity);
-                    c.insert(Name::new(class.clone()));
-
-                    if let Some(name) = &tiled_object.name {
-                        match name.as_str() {
-                            "Green potion" => {
-                                c.insert(Collectable(Potion::Green));
-                            }
-                            "Red potion" => {
-                                c.insert(Collectable(Potion::Red));
-                            }
-                            "Blue potion" => {
-                                c.insert(Collectable(Potion::Blue));
-                            }
-                            "Hammer" => {
-                                c.insert(Collectable(Weapon::Hammer));
-                            }
-                            "Axe" => {
-                                c.insert(Collectable(Weapon::Axe));
-                            }
-                            "Sword" => {
-                                c.insert(Collectable(Weapon::Sword));
-                            }
-                            _ => (),
-                        }
-                    }
-                }
-            }
-            _ => {
-                commands.entity(entity);
-            }
+        let Some(map_transform) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| maps.get(ancestor).ok())
+        else {
+            continue;
         };
-    }
 
-    log::info!("Setup collectables complete.");
+        debug!(
+            "Shape {} at {}: {:?}",
+            name,
+            shape.world_position(map_transform),
+            shape.world_geometry(map_transform)
+        );
+    }
 }
 
-#[derive(Component, Debug, Reflect, InspectorOptions)]
+#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Deserialize)]
 pub struct Player;
 
-#[derive(Component, Debug, Reflect, InspectorOptions)]
+#[derive(Component, Debug, Reflect, InspectorOptions, Deserialize)]
 pub struct Portal;
 
-#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq, Hash)]
+/// Collectables are Tiled objects of class `Potion` or `Weapon`, with the kind of item they are
+/// in a `kind` enum property
+#[derive(
+    Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq, Hash, Deserialize,
+)]
+#[serde(from = "CollectableProperties<T>")]
 pub struct Collectable<T>(T);
 
-#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Deserialize)]
+struct CollectableProperties<T> {
+    kind: T,
+}
+
+impl<T> From<CollectableProperties<T>> for Collectable<T> {
+    fn from(properties: CollectableProperties<T>) -> Self {
+        Self(properties.kind)
+    }
+}
+
+#[derive(
+    Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq, Hash, Deserialize,
+)]
 pub enum Potion {
     Red,
     Green,
@@ -228,7 +170,7 @@ impl fmt::Display for Potion {
     }
 }
 
-#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq)]
+#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum Weapon {
     Sword,
     Hammer,
@@ -246,12 +188,31 @@ impl fmt::Display for Weapon {
 }
 
 // TODO: Inventory is probably better suited as a resource, as there is only one?
-#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq)]
+/// The items carried by the player, or needed to pass through a portal. Portals list them in
+/// their `potion` and `weapon` properties.
+#[derive(Component, Debug, Reflect, InspectorOptions, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(from = "InventoryProperties")]
 pub struct Inventory {
     pub potion: Option<Collectable<Potion>>,
     pub weapon: Option<Collectable<Weapon>>,
 }
 
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct InventoryProperties {
+    potion: Option<Potion>,
+    weapon: Option<Weapon>,
+}
+
+impl From<InventoryProperties> for Inventory {
+    fn from(properties: InventoryProperties) -> Self {
+        Self {
+            potion: properties.potion.map(Collectable),
+            weapon: properties.weapon.map(Collectable),
+        }
+    }
+}
+
 impl Inventory {
     pub fn new() -> Self {
         Self {
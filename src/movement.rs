@@ -2,6 +2,7 @@ use bevy::{
     prelude::*,
     sprite::collide_aabb::{collide, Collision},
 };
+use serde::Deserialize;
 
 use crate::{
     tiled_map::{TiledCollideable, TilemapTileSize},
@@ -10,8 +11,9 @@ use crate::{
 
 const PLAYER_SPEED: f32 = 125.0;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 enum Direction {
+    #[default]
     Stopped,
     Up,
     Down,
@@ -19,21 +21,14 @@ enum Direction {
     Right,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, Default, Deserialize)]
 pub struct Moveable {
+    #[serde(skip)]
     speed: f32,
+    #[serde(skip)]
     direction: Direction,
 }
 
-impl Moveable {
-    pub fn new() -> Self {
-        Self {
-            speed: 0.0,
-            direction: Direction::Stopped,
-        }
-    }
-}
-
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
@@ -146,27 +141,40 @@ fn input_system_touch(
 #[allow(clippy::type_complexity)]
 fn check_collideable(
     mut player_query: Query<
-        (&mut Transform, &mut Moveable, &TilemapTileSize),
+        (
+            &mut Transform,
+            Option<&Parent>,
+            &mut Moveable,
+            &TilemapTileSize,
+        ),
         (With<Player>, Without<TiledCollideable>),
     >,
     collideable_query: Query<
-        (&Transform, &TilemapTileSize),
+        (&GlobalTransform, &TilemapTileSize),
         (With<TiledCollideable>, Without<Player>),
     >,
+    global_transform_query: Query<&GlobalTransform>,
 ) {
-    let Ok((mut player_transform, mut player_moveable, player_size)) =
+    let Ok((mut player_transform, player_parent, mut player_moveable, player_size)) =
         player_query.get_single_mut()
     else {
         return;
     };
 
+    // The player's own global transform doesn't include its movement this frame yet, so its
+    // position is worked out from its parent's.
+    let parent_transform = parent_transform(player_parent, &global_transform_query);
+    let mut player_position = parent_transform.transform_point(player_transform.translation);
+
     for (collideable_transform, collideable_size) in collideable_query.iter() {
+        let collideable_position = collideable_transform.translation();
+
         // TODO: The collideable size should be the width and height of the collideable shape, not
         // just the tile with and height
         if let Some(collision) = collide(
-            player_transform.translation,
+            player_position,
             Vec2::new(player_size.width, player_size.height),
-            collideable_transform.translation,
+            collideable_position,
             Vec2::new(collideable_size.width, collideable_size.height),
         ) {
             // Moving left, collided with right side of wall
@@ -175,8 +183,7 @@ fn check_collideable(
             {
                 // Ensure we don't move in to the wall, as the collision may occur
                 // after we have moved 'into' it (as translation is a vec3 of f32s)
-                player_transform.translation.x =
-                    collideable_transform.translation.x + collideable_size.width;
+                player_position.x = collideable_position.x + collideable_size.width;
                 player_moveable.speed = 0.0;
             };
 
@@ -186,8 +193,7 @@ fn check_collideable(
             {
                 // Ensure we don't move in to the wall, as the collision may occur
                 // after we have moved 'into' it (as translation is a vec3 of f32s)
-                player_transform.translation.x =
-                    collideable_transform.translation.x - collideable_size.width;
+                player_position.x = collideable_position.x - collideable_size.width;
                 player_moveable.speed = 0.0;
             };
 
@@ -197,8 +203,7 @@ fn check_collideable(
             {
                 // Ensure we don't move in to the wall, as the collision may occur
                 // after we have moved 'into' it (as translation is a vec3 of f32s)
-                player_transform.translation.y =
-                    collideable_transform.translation.y - collideable_size.height;
+                player_position.y = collideable_position.y - collideable_size.height;
                 player_moveable.speed = 0.0;
             };
 
@@ -208,35 +213,49 @@ fn check_collideable(
             {
                 // Ensure we don't move in to the wall, as the collision may occur
                 // after we have moved 'into' it (as translation is a vec3 of f32s)
-                player_transform.translation.y =
-                    collideable_transform.translation.y + collideable_size.height;
+                player_position.y = collideable_position.y + collideable_size.height;
                 player_moveable.speed = 0.0;
             };
         }
     }
+
+    let translation = local_translation(&parent_transform, player_position);
+    if player_transform.translation != translation {
+        player_transform.translation = translation;
+    }
 }
 
 #[allow(clippy::type_complexity)]
 fn check_collectable_potion(
     mut player_query: Query<
-        (&mut Transform, &TilemapTileSize, &mut Inventory),
+        (
+            &Transform,
+            Option<&Parent>,
+            &TilemapTileSize,
+            &mut Inventory,
+        ),
         (With<Player>, Without<Collectable<Potion>>),
     >,
     collectable_query: Query<
-        (&Transform, &TilemapTileSize, &Collectable<Potion>),
+        (&GlobalTransform, &TilemapTileSize, &Collectable<Potion>),
         (With<Collectable<Potion>>, Without<Player>),
     >,
+    global_transform_query: Query<&GlobalTransform>,
 ) {
-    let Ok((player_transform, player_size, mut player_inventory)) = player_query.get_single_mut()
+    let Ok((player_transform, player_parent, player_size, mut player_inventory)) =
+        player_query.get_single_mut()
     else {
         return;
     };
 
+    let player_position = parent_transform(player_parent, &global_transform_query)
+        .transform_point(player_transform.translation);
+
     for (collectable_transform, collectable_size, collectable) in collectable_query.iter() {
         if let Some(_collision) = collide(
-            player_transform.translation,
+            player_position,
             Vec2::new(player_size.width, player_size.height),
-            collectable_transform.translation,
+            collectable_transform.translation(),
             Vec2::new(collectable_size.width, collectable_size.height),
         ) {
             if player_inventory.potion != Some(*collectable) {
@@ -249,24 +268,34 @@ fn check_collectable_potion(
 #[allow(clippy::type_complexity)]
 fn check_collectable_weapon(
     mut player_query: Query<
-        (&mut Transform, &TilemapTileSize, &mut Inventory),
+        (
+            &Transform,
+            Option<&Parent>,
+            &TilemapTileSize,
+            &mut Inventory,
+        ),
         (With<Player>, Without<Collectable<Weapon>>),
     >,
     collectable_query: Query<
-        (&Transform, &TilemapTileSize, &Collectable<Weapon>),
+        (&GlobalTransform, &TilemapTileSize, &Collectable<Weapon>),
         (With<Collectable<Weapon>>, Without<Player>),
     >,
+    global_transform_query: Query<&GlobalTransform>,
 ) {
-    let Ok((player_transform, player_size, mut player_inventory)) = player_query.get_single_mut()
+    let Ok((player_transform, player_parent, player_size, mut player_inventory)) =
+        player_query.get_single_mut()
     else {
         return;
     };
 
+    let player_position = parent_transform(player_parent, &global_transform_query)
+        .transform_point(player_transform.translation);
+
     for (collectable_transform, collectable_size, collectable) in collectable_query.iter() {
         if let Some(_collision) = collide(
-            player_transform.translation,
+            player_position,
             Vec2::new(player_size.width, player_size.height),
-            collectable_transform.translation,
+            collectable_transform.translation(),
             Vec2::new(collectable_size.width, collectable_size.height),
         ) {
             if player_inventory.weapon != Some(*collectable) {
@@ -279,26 +308,43 @@ fn check_collectable_weapon(
 #[allow(clippy::type_complexity)]
 fn check_portal(
     mut player_query: Query<
-        (&mut Transform, &TilemapTileSize, &mut Moveable, &Inventory),
+        (
+            &mut Transform,
+            Option<&Parent>,
+            &TilemapTileSize,
+            &mut Moveable,
+            &Inventory,
+        ),
         (With<Player>, Without<Portal>),
     >,
     mut portal_query: Query<
-        (&Transform, &TilemapTileSize, &mut Portal, &Inventory),
+        (&GlobalTransform, &TilemapTileSize, &mut Portal, &Inventory),
         (With<Portal>, Without<Player>),
     >,
+    global_transform_query: Query<&GlobalTransform>,
 ) {
-    let Ok((mut player_transform, player_size, mut player_moveable, player_inventory)) =
-        player_query.get_single_mut()
+    let Ok((
+        mut player_transform,
+        player_parent,
+        player_size,
+        mut player_moveable,
+        player_inventory,
+    )) = player_query.get_single_mut()
     else {
         println!("Did not find player");
         return;
     };
 
+    let parent_transform = parent_transform(player_parent, &global_transform_query);
+    let player_position = parent_transform.transform_point(player_transform.translation);
+
     for (portal_transform, portal_size, _portal, portal_inventory) in portal_query.iter_mut() {
+        let portal_position = portal_transform.translation();
+
         if let Some(collision) = collide(
-            player_transform.translation,
+            player_position,
             Vec2::new(player_size.width, player_size.height),
-            portal_transform.translation,
+            portal_position,
             Vec2::new(portal_size.width, portal_size.height),
         ) {
             if let (Some(pl_weapon), Some(pl_potion), Some(po_weapon), Some(po_potion)) = (
@@ -321,16 +367,24 @@ fn check_portal(
             match collision {
                 Collision::Top => {
                     if matches!(player_moveable.direction, Direction::Down) {
-                        player_transform.translation.y = portal_transform.translation.y
-                            - (portal_size.height - (player_size.height * 1.5));
+                        let y =
+                            portal_position.y - (portal_size.height - (player_size.height * 1.5));
+                        player_transform.translation = local_translation(
+                            &parent_transform,
+                            Vec3::new(player_position.x, y, player_position.z),
+                        );
                         // Make the player 'pop' out the other side
                         player_moveable.speed = PLAYER_SPEED / 2.;
                     }
                 }
                 Collision::Bottom => {
                     if matches!(player_moveable.direction, Direction::Up) {
-                        player_transform.translation.y = portal_transform.translation.y
-                            + (portal_size.height - (player_size.height * 1.5));
+                        let y =
+                            portal_position.y + (portal_size.height - (player_size.height * 1.5));
+                        player_transform.translation = local_translation(
+                            &parent_transform,
+                            Vec3::new(player_position.x, y, player_position.z),
+                        );
                         // Make the player 'pop' out the other side
                         player_moveable.speed = PLAYER_SPEED / 2.;
                     }
@@ -340,3 +394,23 @@ fn check_portal(
         }
     }
 }
+
+/// The global transform of an entity's parent. The map content is nested under its map and
+/// layer entities, so positions are compared in world space.
+fn parent_transform(
+    parent: Option<&Parent>,
+    global_transform_query: &Query<&GlobalTransform>,
+) -> GlobalTransform {
+    parent
+        .and_then(|parent| global_transform_query.get(parent.get()).ok())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// The translation that places an entity with the given parent transform at a world position
+fn local_translation(parent_transform: &GlobalTransform, position: Vec3) -> Vec3 {
+    parent_transform
+        .affine()
+        .inverse()
+        .transform_point3(position)
+}
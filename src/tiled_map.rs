@@ -1,19 +1,30 @@
 use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, TAU};
 use std::io::{Cursor, ErrorKind};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use bevy::math::{ivec3, vec2, Vec2};
-use bevy::prelude::{Component, IVec3, Name, ResMut, Update, Vec3, Visibility};
+use bevy::math::{ivec2, ivec3, vec2, vec3, EulerRot, IRect, IVec2, Vec2};
+use bevy::prelude::{
+    apply_deferred, BuildChildren, ChildBuilder, Component, Entity, EventWriter, IVec3,
+    InheritedVisibility, IntoSystemConfigs, IntoSystemSetConfigs, Name, PostUpdate, Quat,
+    SpatialBundle, SystemSet, Update, Vec3, ViewVisibility, Visibility,
+};
 use bevy::reflect::Reflect;
 use bevy::render::color::Color;
-use bevy::sprite::{Sprite, SpriteBundle, SpriteSheetBundle, TextureAtlas, TextureAtlasSprite};
+use bevy::sprite::{
+    Anchor, Sprite, SpriteBundle, SpriteSheetBundle, TextureAtlas, TextureAtlasSprite,
+};
+use bevy::transform::TransformSystem;
 use bevy::{
-    asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt},
+    asset::{
+        io::Reader, AssetLoader, AssetPath, AsyncReadExt, LoadContext, LoadDirectError,
+        RecursiveDependencyLoadState,
+    },
     log,
     prelude::{
-        Added, Asset, AssetApp, Assets, Bundle, Commands, GlobalTransform, Handle, Image, Plugin,
-        Query, Res, Transform,
+        Asset, AssetApp, AssetServer, Assets, Bundle, Commands, GlobalTransform, Handle, Image,
+        Plugin, Query, Res, Transform,
     },
     reflect::TypePath,
     utils::BoxedFuture,
@@ -21,16 +32,45 @@ use bevy::{
 
 use bevy_inspector_egui::prelude::*;
 use bevy_simple_tilemap::{prelude::*, TileFlags};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tiled::TileLayer;
-
-const SCALE: f32 = 3.0;
+use tiled::{ChunkData, TileLayer};
+
+mod animation;
+mod attributes;
+mod classes;
+mod collection;
+mod events;
+mod grid;
+mod json;
+mod parallax;
+mod project;
+mod properties;
+mod reload;
+mod style;
+mod ysort;
+
+pub use animation::{TiledAnimatedTiles, TiledAnimation};
+pub use classes::TiledClassAppExt;
+pub use events::TiledMapEvent;
+pub use grid::MapGrid;
+pub use parallax::TiledParallax;
+pub use project::TiledProject;
+pub use properties::{TiledProperties, TiledPropertyValue};
+pub use reload::TiledReloadAppExt;
+pub use style::TiledLayerStyle;
+pub use ysort::{YSort, YSORT_PROPERTY};
 
 pub struct TilemapSize {
     pub columns: usize,
     pub rows: usize,
     pub width: usize,
     pub height: usize,
+    /// The Tiled coordinates of the map's top left tile, which infinite maps can place left of or
+    /// above 0, 0
+    pub origin: IVec2,
+    /// How the map's tiles are laid out
+    pub grid: MapGrid,
 }
 
 /// TimemapTileSize contains the width and height of a tile
@@ -62,52 +102,358 @@ impl Plugin for TiledMapPlugin {
         app.init_asset::<TiledMap>()
             .register_asset_loader(TiledLoader)
             .register_type::<TiledMapBundle>()
+            .register_type::<TiledMapState>()
+            .register_type::<TiledProperties>()
+            .register_type::<TiledAnimation>()
+            .register_type::<TiledParallax>()
+            .register_type::<TiledLayerStyle>()
+            .register_type::<YSort>()
+            .add_event::<TiledMapEvent>()
+            .init_resource::<classes::TiledClassRegistry>()
+            .configure_sets(
+                Update,
+                (
+                    TiledMapSystem::Reload,
+                    TiledMapSystem::Spawn,
+                    TiledMapSystem::Restore,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Update,
                 (
-                    process_map_layers,
-                    process_map_collideables,
-                    process_map_object_sprites,
-                    process_map_object_shapes,
+                    (reload::reload_modified_maps, reload::retry_failed_maps)
+                        .in_set(TiledMapSystem::Reload),
+                    // The content of reloading maps is despawned before their new content is
+                    // spawned, whichever order the systems were added in.
+                    apply_deferred
+                        .after(TiledMapSystem::Reload)
+                        .before(TiledMapSystem::Spawn),
+                    (
+                        style::restyle_layers,
+                        (animation::animate_tiles, animation::animate_sprites),
+                    )
+                        .chain()
+                        .after(TiledMapSystem::Restore),
+                    (
+                        process_maps,
+                        apply_deferred,
+                        classes::insert_class_components,
+                        apply_deferred,
+                        events::send_spawned_events,
+                    )
+                        .chain()
+                        .in_set(TiledMapSystem::Spawn),
                 ),
+            )
+            .add_systems(
+                PostUpdate,
+                (parallax::scroll_parallax_layers, ysort::y_sort)
+                    .before(TransformSystem::TransformPropagate),
             );
     }
 }
 
+/// The stages the plugin goes through each frame to (re)spawn map content
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TiledMapSystem {
+    /// Despawn the content of maps whose asset was modified
+    Reload,
+    /// Spawn the content of maps whose asset has finished loading
+    Spawn,
+    /// Runs once the spawned content exists
+    Restore,
+}
+
 #[derive(TypePath, Asset)]
 pub struct TiledMap {
     pub map: tiled::Map,
-    pub tilemap_textures: HashMap<usize, Handle<Image>>,
+    pub settings: TiledLoaderSettings,
+    /// The custom types of the project given in the settings
+    pub project: Option<TiledProject>,
+    /// The texture atlas of each tileset, created once when the map loads and shared by every
+    /// layer and object drawn from it. The atlases hold the handles of the tileset images.
+    pub texture_atlases: HashMap<usize, Handle<TextureAtlas>>,
+    /// The image of each image layer, by layer ID
+    pub image_layer_textures: HashMap<u32, Handle<Image>>,
+    /// The attributes of the map that tiled doesn't read
+    pub attributes: attributes::MapAttributes,
+    /// The tiles the map covers, in Tiled's tile coordinates. The tiles of infinite maps are
+    /// stored in chunks, which can be anywhere around 0, 0.
+    pub tile_bounds: IRect,
+    /// The index in its tileset's texture atlas of each tile of an image collection. The tiles of
+    /// other tilesets are at the index of their ID.
     pub tile_image_offsets: HashMap<(usize, tiled::TileId), u32>,
 }
 
+impl TiledMap {
+    /// Complete the custom properties of a map, layer, tile or object of the given class with
+    /// the defaults and enum names from the project.
+    fn with_project_types(
+        &self,
+        class: Option<&str>,
+        mut properties: TiledProperties,
+    ) -> TiledProperties {
+        if let Some(project) = &self.project {
+            project.apply(class, &mut properties);
+        }
+
+        properties
+    }
+
+    /// The size of the map, and of a tileset's image if given
+    fn tilemap_size(&self, tileset: Option<&tiled::Tileset>) -> TilemapSize {
+        TilemapSize {
+            columns: tileset.map_or(0, |tileset| tileset.columns as usize),
+            rows: tileset.map_or(0, tileset_rows),
+            width: self.tile_bounds.width() as usize,
+            height: self.tile_bounds.height() as usize,
+            origin: self.tile_bounds.min,
+            grid: MapGrid::new(&self.map, &self.attributes, self.tile_bounds),
+        }
+    }
+
+    /// The parallax of a layer, scrolling from the map's parallax origin. The parallax factors of
+    /// its groups multiply its own.
+    fn layer_parallax(
+        &self,
+        layer: &tiled::Layer,
+        inherited: &InheritedStyle,
+    ) -> Option<TiledParallax> {
+        let tilemap_size = self.tilemap_size(None);
+        let parallax_origin = self.attributes.parallax_origin;
+        let origin = Point::from_tiled_pixel(
+            &tilemap_size,
+            &self.settings,
+            parallax_origin.x,
+            parallax_origin.y,
+        );
+
+        // The layer is placed within its groups, so the origin is too.
+        TiledParallax::new(
+            inherited.parallax * vec2(layer.parallax_x, layer.parallax_y),
+            vec2(origin.x, origin.y) - inherited.offset,
+            layer_offset(layer, self.settings.scale),
+        )
+    }
+
+    /// The position of the center of a tile, given in Tiled's tile coordinates, relative to the
+    /// map entity
+    pub fn tile_to_world(&self, x: i32, y: i32) -> Vec2 {
+        let tilemap_size = self.tilemap_size(None);
+        let center = tilemap_size.grid.cell_center(ivec2(x, y));
+        let point = Point::from_map_position(&tilemap_size, &self.settings, center);
+
+        vec2(point.x, point.y)
+    }
+
+    /// The position of a point, given in Tiled's object coordinates, relative to the map entity
+    pub fn object_to_world(&self, x: f32, y: f32) -> Vec2 {
+        let point = Point::from_tiled_object(&self.tilemap_size(None), &self.settings, x, y);
+
+        vec2(point.x, point.y)
+    }
+
+    /// The y-sorting of an object layer's objects, over the first half of the layer's z spacing
+    /// from the top of the map to its bottom, if the layer or the map turns it on
+    fn layer_y_sort(&self, layer: &tiled::Layer, layer_index: usize) -> Option<YSort> {
+        let y_sort = TiledProperties::from(&layer.properties)
+            .get_bool(YSORT_PROPERTY)
+            .unwrap_or(self.settings.y_sort);
+
+        if !y_sort {
+            return None;
+        }
+
+        let tilemap_size = self.tilemap_size(None);
+        let map_height = tilemap_size.grid.pixel_size().y;
+        let top = Point::from_map_position(&tilemap_size, &self.settings, Vec2::ZERO);
+        let bottom = Point::from_map_position(&tilemap_size, &self.settings, vec2(0.0, map_height));
+
+        let z = self.settings.layer_z(layer_index, 0.0);
+        let depth = self.settings.layer_z(layer_index, 1.0) - z;
+
+        Some(YSort::new(z, depth, top.y, bottom.y))
+    }
+
+    /// The index of a tile's sprite in its tileset's texture atlas
+    pub fn sprite_index(&self, tileset_index: usize, tile_id: tiled::TileId) -> u32 {
+        self.tile_image_offsets
+            .get(&(tileset_index, tile_id))
+            .copied()
+            .unwrap_or(tile_id)
+    }
+}
+
+/// Which point of the map is placed at the world offset
+#[derive(Reflect, Serialize, Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TiledMapAnchor {
+    #[default]
+    Center,
+    TopLeft,
+    BottomLeft,
+}
+
+/// TiledLoaderSettings controls how the map is placed in the world when it is spawned
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone)]
+pub struct TiledLoaderSettings {
+    /// The number of world units per Tiled pixel
+    pub scale: f32,
+    /// The point of the map that is placed at the offset
+    pub anchor: TiledMapAnchor,
+    /// The world position of the anchor
+    pub offset: Vec2,
+    /// The asset path of the Tiled project (or exported property types) holding the custom
+    /// classes and enums used by the map
+    pub project: Option<String>,
+    /// The distance in z between one layer of the map and the next. The content of a layer is
+    /// ordered within the first half of it, so sprites placed in the second half are drawn
+    /// between the two layers.
+    pub layer_z_spacing: f32,
+    /// Y-sort the objects of every object layer, unless a layer turns it off with its `ysort`
+    /// property
+    pub y_sort: bool,
+}
+
+impl Default for TiledLoaderSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            anchor: TiledMapAnchor::default(),
+            offset: Vec2::ZERO,
+            project: None,
+            layer_z_spacing: 1.0,
+            y_sort: false,
+        }
+    }
+}
+
+impl TiledLoaderSettings {
+    /// The world z of a layer's content, `depth` of the way through the layer from 0 to 1
+    fn layer_z(&self, layer_index: usize, depth: f32) -> f32 {
+        (layer_index as f32 + depth.clamp(0.0, 1.0) / 2.0) * self.layer_z_spacing
+    }
+
+    /// The world position of the top left corner of the map.
+    fn map_corner(&self, tilemap_size: &TilemapSize) -> Vec2 {
+        let map_size = tilemap_size.grid.pixel_size() * self.scale;
+        let (width, height) = (map_size.x, map_size.y);
+
+        let corner = match self.anchor {
+            TiledMapAnchor::Center => vec2(-width / 2.0, height / 2.0),
+            TiledMapAnchor::TopLeft => Vec2::ZERO,
+            TiledMapAnchor::BottomLeft => vec2(0.0, height),
+        };
+
+        corner + self.offset
+    }
+}
+
+/// The map entity. Everything spawned for the map is a descendant of it, so moving or despawning
+/// (recursively) the map entity moves or removes all of its content.
 #[derive(Default, Bundle, Reflect)]
 pub struct TiledMapBundle {
     pub tiled_map: Handle<TiledMap>,
+    pub state: TiledMapState,
     pub transform: Transform,
     pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
 }
 
-struct BytesResourceReader {
-    bytes: Arc<[u8]>,
+/// TiledMapState tracks whether the content of a map entity has been spawned
+#[derive(Reflect, Component, Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TiledMapState {
+    /// Waiting for the map asset and all of its textures to finish loading
+    #[default]
+    Loading,
+    /// The map content has been spawned as children of the map entity
+    Spawned,
+    /// The map asset or one of its dependencies failed to load
+    Failed,
 }
 
-impl BytesResourceReader {
-    fn new(bytes: &[u8]) -> Self {
-        Self {
-            bytes: Arc::from(bytes),
-        }
-    }
+/// Feeds [tiled::Loader] with the files read ahead of time by [read_tiled_files]: the map and
+/// every file it references (external tilesets, object templates). tiled reads them
+/// synchronously, so they can't be fetched through the [LoadContext] as tiled asks for them.
+struct BytesResourceReader {
+    files: HashMap<PathBuf, Arc<[u8]>>,
 }
 
 impl tiled::ResourceReader for BytesResourceReader {
     type Resource = Cursor<Arc<[u8]>>;
     type Error = std::io::Error;
 
-    fn read_from(&mut self, _path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
-        // In this case, the path is ignored because the byte data is already provided.
-        Ok(Cursor::new(self.bytes.clone()))
+    fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+        self.files
+            .get(path)
+            .map(|bytes| Cursor::new(bytes.clone()))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("{} was not read with the map", path.display()),
+                )
+            })
+    }
+}
+
+/// Read a map and every file it references, and the files they reference in turn, by the paths
+/// tiled will ask for them: relative to the directory of the file that references them, which
+/// for the map is relative to the assets directory. Reading them through the load context records
+/// them as loader dependencies, so editing a shared tileset reloads every map that uses it.
+///
+/// Files in the Tiled JSON formats are converted to XML, and class properties are flattened, see
+/// [json] and [properties].
+async fn read_tiled_files(
+    load_context: &mut LoadContext<'_>,
+    map_path: &Path,
+    map_bytes: Vec<u8>,
+) -> Result<HashMap<PathBuf, Arc<[u8]>>, std::io::Error> {
+    let mut files = HashMap::default();
+    let mut pending = vec![(map_path.to_path_buf(), Some(map_bytes))];
+
+    while let Some((path, bytes)) = pending.pop() {
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => load_context
+                .read_asset_bytes(path.clone())
+                .await
+                .map_err(|e| std::io::Error::new(ErrorKind::NotFound, e))?,
+        };
+
+        let mut bytes = if json::is_json(&path) {
+            json::to_xml(&path, &bytes)
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?
+        } else {
+            bytes
+        };
+
+        // tiled can't parse class properties, so they are flattened into ones it can.
+        if properties::has_class_properties(&bytes) {
+            bytes = properties::flatten_class_properties(&bytes)
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+        }
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let references = attributes::referenced_files(&bytes)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+
+        for reference in references {
+            let reference = directory.join(reference);
+
+            if !files.contains_key(&reference)
+                && reference != path
+                && !pending.iter().any(|(pending, _)| *pending == reference)
+            {
+                pending.push((reference, None));
+            }
+        }
+
+        files.insert(path, bytes.into());
     }
+
+    Ok(files)
 }
 
 pub struct TiledLoader;
@@ -117,53 +463,125 @@ pub enum TiledAssetLoaderError {
     /// An [IO](std::io) Error
     #[error("Could not load Tiled file: {0}")]
     Io(#[from] std::io::Error),
+    /// A [Tiled](tiled) parsing error
+    #[error("Could not load TMX map: {0}")]
+    Tiled(#[from] tiled::Error),
+    /// The project given in the settings could not be loaded
+    #[error("Could not load Tiled project: {0}")]
+    Project(#[from] project::TiledProjectError),
+    /// The image of a tile in an image collection tileset could not be loaded
+    #[error("Could not load tile image: {0}")]
+    TileImage(#[from] LoadDirectError),
+    /// The image of a tile in an image collection tileset can't be packed into a texture atlas
+    #[error("Could not pack tile image {0} into a texture atlas")]
+    TileImageFormat(String),
 }
 
 impl AssetLoader for TiledLoader {
     type Asset = TiledMap;
-    type Settings = ();
+    type Settings = TiledLoaderSettings;
     type Error = TiledAssetLoaderError;
 
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader,
-        _settings: &'a Self::Settings,
+        settings: &'a Self::Settings,
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
 
-            let mut loader = tiled::Loader::with_cache_and_reader(
+            let map_path = load_context.path().to_path_buf();
+            let files = read_tiled_files(load_context, &map_path, bytes).await?;
+
+            // tiled skips some of the attributes of the map, so they are read from it separately.
+            let map_directory = map_path.parent().unwrap_or(Path::new(""));
+            let attributes = attributes::MapAttributes::from_xml(&files[&map_path], |source| {
+                files
+                    .get(&map_directory.join(source))
+                    .map(|bytes| &bytes[..])
+            })
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+
+            let map = tiled::Loader::with_cache_and_reader(
                 tiled::DefaultResourceCache::new(),
-                BytesResourceReader::new(&bytes),
-            );
-            let map = loader.load_tmx_map(load_context.path()).map_err(|e| {
-                std::io::Error::new(ErrorKind::Other, format!("Could not load TMX map: {e}"))
-            })?;
+                BytesResourceReader { files },
+            )
+            .load_tmx_map(map_path)?;
+
+            // The project is read rather than loaded as an asset, so the custom types are at hand
+            // when the map is spawned. It is still a dependency, so editing it reloads the map.
+            let project = match &settings.project {
+                Some(project_path) => {
+                    let bytes = load_context
+                        .read_asset_bytes(project_path.clone())
+                        .await
+                        .map_err(|e| std::io::Error::new(ErrorKind::NotFound, e))?;
+                    Some(TiledProject::from_slice(&bytes)?)
+                }
+                None => None,
+            };
 
-            let mut tilemap_textures = HashMap::default();
-            let tile_image_offsets = HashMap::default();
+            let mut texture_atlases = HashMap::default();
+            let mut tile_image_offsets = HashMap::default();
 
             for (tileset_index, tileset) in map.tilesets().iter().enumerate() {
                 if let Some(img) = &tileset.image {
-                    // The load context path is the TMX file itself. If the file is at the root of the
-                    // assets/ directory structure then the tmx_dir will be empty, which is fine.
-                    let tmx_dir = load_context
-                        .path()
-                        .parent()
-                        .expect("The asset load context was empty.");
-                    let tile_path = tmx_dir.join(&img.source);
-                    let asset_path = AssetPath::from(tile_path);
-                    let texture: Handle<Image> = load_context.load(asset_path.clone());
-
-                    tilemap_textures.insert(tileset_index, texture);
+                    // tiled resolves the image source relative to the file that declared it (the
+                    // TMX or an external TSX), which is already relative to the assets/ directory.
+                    let asset_path = AssetPath::from(img.source.clone());
+                    let texture: Handle<Image> = load_context.load(asset_path);
+
+                    let texture_atlas = load_context.add_labeled_asset(
+                        format!("tileset{tileset_index}"),
+                        tileset_texture_atlas(texture, tileset),
+                    );
+
+                    texture_atlases.insert(tileset_index, texture_atlas);
+                } else if let Some(packed) =
+                    collection::pack_image_collection(load_context, tileset).await?
+                {
+                    let size = packed.image.size().as_vec2();
+                    let texture = load_context
+                        .add_labeled_asset(format!("tileset{tileset_index}/image"), packed.image);
+
+                    let mut texture_atlas = TextureAtlas::new_empty(texture, size);
+                    for (tile_id, rect) in packed.rects {
+                        let index = texture_atlas.add_texture(rect);
+                        tile_image_offsets.insert((tileset_index, tile_id), index as u32);
+                    }
+
+                    let texture_atlas = load_context
+                        .add_labeled_asset(format!("tileset{tileset_index}"), texture_atlas);
+
+                    texture_atlases.insert(tileset_index, texture_atlas);
                 }
             }
 
+            let mut image_layer_textures = HashMap::default();
+
+            for image_layer in image_layers(map.layers()) {
+                let tiled::LayerType::Image(image) = image_layer.layer_type() else {
+                    continue;
+                };
+
+                if let Some(img) = &image.image {
+                    let texture: Handle<Image> = load_context.load(img.source.clone());
+                    image_layer_textures.insert(image_layer.id(), texture);
+                }
+            }
+
+            let tile_bounds = tile_bounds(&map);
+
             let asset_map = TiledMap {
                 map,
-                tilemap_textures,
+                settings: settings.clone(),
+                project,
+                texture_atlases,
+                image_layer_textures,
+                attributes,
+                tile_bounds,
                 tile_image_offsets,
             };
 
@@ -174,414 +592,929 @@ impl AssetLoader for TiledLoader {
     }
 
     fn extensions(&self) -> &[&str] {
-        static EXTENSIONS: &[&str] = &["tmx"];
+        static EXTENSIONS: &[&str] = &["tmx", "tmj"];
         EXTENSIONS
     }
 }
 
-pub fn process_map_layers(
-    mut commands: Commands,
-    mut map_query: Query<&Handle<TiledMap>>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    maps: Res<Assets<TiledMap>>,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
-) {
-    // If we have new map entities add them to the changed_maps list.
-    for _new_map in new_maps.iter() {
-        for map_handle in map_query.iter_mut() {
-            if let Some(tiled_map) = maps.get(map_handle) {
-                for (tileset_index, tileset) in tiled_map.map.tilesets().iter().enumerate() {
-                    let Some(tilemap_texture) = tiled_map.tilemap_textures.get(&tileset_index)
-                    else {
-                        log::warn!("Skipped creating layer with missing tilemap textures.");
-                        continue;
-                    };
+/// The tiles covered by a map. Those of an infinite map are the ones used by its tile layers.
+fn tile_bounds(map: &tiled::Map) -> IRect {
+    let map_bounds = IRect::new(0, 0, map.width as i32, map.height as i32);
 
-                    let tile_size = TilemapTileSize {
-                        width: tileset.tile_width as f32,
-                        height: tileset.tile_height as f32,
-                    };
+    if !map.infinite() {
+        return map_bounds;
+    }
 
-                    let tile_spacing = TilemapSpacing {
-                        x: tileset.spacing as f32,
-                        y: tileset.spacing as f32,
-                    };
+    let mut tiles = vec![];
+    collect_infinite_tiles(map.layers(), &mut tiles);
 
-                    let tilemap_size = TilemapSize {
-                        columns: tileset.columns as usize,
-                        rows: (tileset.tilecount / tileset.columns) as usize,
-                        width: tiled_map.map.width as usize,
-                        height: tiled_map.map.height as usize,
-                    };
+    let mut tiles = tiles.into_iter();
+    let Some(first) = tiles.next() else {
+        return map_bounds;
+    };
+
+    tiles.fold(
+        IRect::from_corners(first, first + IVec2::ONE),
+        |bounds, tile| bounds.union(IRect::from_corners(tile, tile + IVec2::ONE)),
+    )
+}
 
-                    // Once materials have been created/added we need to then create the layers.
-                    for (layer_index, layer) in tiled_map.map.layers().enumerate() {
-                        let tiled::LayerType::Tiles(tile_layer) = layer.layer_type() else {
-                            continue;
-                        };
-
-                        let Some(tiles) =
-                            build_tiles(&tile_layer, &tilemap_size, tileset_index, layer_index)
-                        else {
-                            log::info!(
-                                "No tiles for layer {} [{}]",
-                                layer.name.clone(),
-                                layer_index,
-                            );
-                            continue;
-                        };
-
-                        let mut tilemap = TileMap::default();
-                        tilemap.set_tiles(tiles);
-
-                        let texture_atlas = TextureAtlas::from_grid(
-                            tilemap_texture.clone(),
-                            vec2(tile_size.width, tile_size.height),
-                            tilemap_size.columns,
-                            tilemap_size.rows,
-                            Some(vec2(tile_spacing.x, tile_spacing.y)),
-                            None,
-                        );
-
-                        let texture_atlas_handle = texture_atlases.add(texture_atlas);
-                        let map_origin = Point::get_map_origin(&tilemap_size, &tile_size, SCALE);
-                        let scale = Vec3::splat(SCALE);
-                        let translation = Vec3::new(map_origin.x, map_origin.y, 0.0);
-
-                        let tilemap_bundle = TileMapBundle {
-                            tilemap,
-                            texture_atlas: texture_atlas_handle,
-                            transform: Transform {
-                                scale,
-                                translation,
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        };
-
-                        commands
-                            .spawn(tilemap_bundle)
-                            .insert(Name::new(layer.name.clone()))
-                            .insert(tile_size.scaled(SCALE));
+/// The position of every tile in the infinite tile layers, including those within groups
+fn collect_infinite_tiles<'map>(
+    layers: impl Iterator<Item = tiled::Layer<'map>>,
+    tiles: &mut Vec<IVec2>,
+) {
+    for layer in layers {
+        match layer.layer_type() {
+            tiled::LayerType::Tiles(TileLayer::Infinite(infinite)) => {
+                let chunk_size = ivec2(ChunkData::WIDTH as i32, ChunkData::HEIGHT as i32);
+
+                for ((chunk_x, chunk_y), chunk) in infinite.chunk_data() {
+                    let chunk_origin = ivec2(chunk_x, chunk_y) * chunk_size;
+
+                    for x in 0..chunk_size.x {
+                        for y in 0..chunk_size.y {
+                            if chunk.get_tile_data(x, y).is_some() {
+                                tiles.push(chunk_origin + ivec2(x, y));
+                            }
+                        }
                     }
                 }
             }
+            tiled::LayerType::Group(group) => collect_infinite_tiles(group.layers(), tiles),
+            _ => (),
         }
     }
 }
 
-pub fn process_map_collideables(
+/// Every image layer, including those within groups
+fn image_layers<'map>(layers: impl Iterator<Item = tiled::Layer<'map>>) -> Vec<tiled::Layer<'map>> {
+    let mut found = vec![];
+
+    for layer in layers {
+        match layer.layer_type() {
+            tiled::LayerType::Image(_) => found.push(layer),
+            tiled::LayerType::Group(group) => found.extend(image_layers(group.layers())),
+            _ => (),
+        }
+    }
+
+    found
+}
+
+/// Spawn the content of each map entity as its children, once the map and all of its textures
+/// have finished loading. Each Tiled layer gets a child entity of its own, which in turn holds
+/// that layer's tiles, collideables and objects, or the layers of a group.
+pub fn process_maps(
     mut commands: Commands,
-    mut map_query: Query<&Handle<TiledMap>>,
+    asset_server: Res<AssetServer>,
     maps: Res<Assets<TiledMap>>,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
+    mut map_query: Query<(Entity, &Handle<TiledMap>, &mut TiledMapState)>,
+    mut events: EventWriter<TiledMapEvent>,
 ) {
-    // If we have new map entities add them to the changed_maps list.
-    for _new_map in new_maps.iter() {
-        for map_handle in map_query.iter_mut() {
-            if let Some(tiled_map) = maps.get(map_handle) {
-                for tileset in tiled_map.map.tilesets().iter() {
-                    let tile_size = TilemapTileSize {
-                        width: tileset.tile_width as f32,
-                        height: tileset.tile_height as f32,
-                    };
-
-                    let tilemap_size = TilemapSize {
-                        columns: tileset.columns as usize,
-                        rows: (tileset.tilecount / tileset.columns) as usize,
-                        width: tiled_map.map.width as usize,
-                        height: tiled_map.map.height as usize,
-                    };
+    for (map_entity, map_handle, mut state) in map_query.iter_mut() {
+        if *state != TiledMapState::Loading {
+            continue;
+        }
 
-                    for (layer_index, layer) in tiled_map.map.layers().enumerate() {
-                        let tiled::LayerType::Tiles(tile_layer) = layer.layer_type() else {
-                            continue;
-                        };
-
-                        let Some(collideables) =
-                            build_collideables(&tilemap_size, &tile_size, &tile_layer, layer_index)
-                        else {
-                            continue;
-                        };
-
-                        for collideable in collideables {
-                            let color = Color::rgba(0.25, 0.25, 0.75, 0.5);
-                            let custom_size = Some(Vec2::new(
-                                tile_size.scaled(SCALE).width,
-                                tile_size.scaled(SCALE).height,
-                            ));
-                            let translation = Vec3 {
-                                x: collideable.collision_point.x,
-                                y: collideable.collision_point.y,
-                                z: 30.0,
-                            };
-
-                            commands
-                                // The sprite bundle just renders a transparent colored
-                                // rectangle showing where this non sprite object exists
-                                // e.g a collision shape
-                                .spawn(SpriteBundle {
-                                    sprite: Sprite {
-                                        color,
-                                        custom_size,
-                                        ..Default::default()
-                                    },
-                                    transform: Transform {
-                                        translation,
-                                        ..Default::default()
-                                    },
-                                    // Set to visible if you want to see the collision
-                                    // areas for debugging
-                                    visibility: Visibility::Hidden,
-                                    ..Default::default()
-                                })
-                                .insert(tile_size.scaled(SCALE))
-                                .insert(collideable);
-                        }
-                    }
-                }
+        let loaded = match asset_server.get_recursive_dependency_load_state(map_handle) {
+            // Maps added to the assets directly are not tracked by the asset server.
+            None => maps.contains(map_handle),
+            Some(RecursiveDependencyLoadState::Loaded) => true,
+            Some(RecursiveDependencyLoadState::Failed) => {
+                log::error!("Failed to load map or its textures for {:?}", map_entity);
+                *state = TiledMapState::Failed;
+                continue;
             }
+            Some(_) => false,
+        };
+
+        if !loaded {
+            continue;
         }
+
+        let Some(tiled_map) = maps.get(map_handle) else {
+            continue;
+        };
+
+        events.send(TiledMapEvent::Loaded {
+            entity: map_entity,
+            handle: map_handle.clone(),
+        });
+
+        *state = TiledMapState::Spawned;
+
+        commands
+            .entity(map_entity)
+            .insert(tiled_map.with_project_types(
+                tiled_map.map.user_type.as_deref(),
+                TiledProperties::from(&tiled_map.map.properties),
+            ))
+            .with_children(|parent| {
+                spawn_layers(
+                    parent,
+                    tiled_map,
+                    tiled_map.map.layers(),
+                    &mut 0,
+                    &InheritedStyle::default(),
+                );
+            });
     }
 }
 
-pub fn process_map_object_sprites(
-    mut commands: Commands,
-    mut map_query: Query<&Handle<TiledMap>>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    maps: Res<Assets<TiledMap>>,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
+/// What a layer takes on from the group layers it is in, which Tiled combines with its own
+#[derive(Debug, Clone, Copy)]
+struct InheritedStyle {
+    /// The offset of the groups, in world space
+    offset: Vec2,
+    /// The tint of the groups, with their opacity as its alpha
+    color: Color,
+    parallax: Vec2,
+}
+
+impl Default for InheritedStyle {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            color: Color::WHITE,
+            parallax: Vec2::ONE,
+        }
+    }
+}
+
+impl InheritedStyle {
+    /// The style the layers of a group inherit
+    fn with_group(&self, group: &tiled::Layer, scale: f32) -> Self {
+        Self {
+            offset: self.offset + layer_offset(group, scale),
+            color: self.color_of(group),
+            parallax: self.parallax * vec2(group.parallax_x, group.parallax_y),
+        }
+    }
+
+    /// The colour to draw a layer's content with, from its own tint and opacity and its groups'
+    fn color_of(&self, layer: &tiled::Layer) -> Color {
+        TiledLayerStyle::from(layer).apply(self.color)
+    }
+}
+
+/// The offset of a layer, in world space. Tiled's offset points down.
+fn layer_offset(layer: &tiled::Layer, scale: f32) -> Vec2 {
+    vec2(layer.offset_x, -layer.offset_y) * scale
+}
+
+/// Spawn an entity for each layer, in the order Tiled draws them. Each layer entity is moved by
+/// the layer's offset, hidden along with it and holds its style. The layers of a group are spawned
+/// as children of the group's entity.
+fn spawn_layers<'map>(
+    parent: &mut ChildBuilder,
+    tiled_map: &TiledMap,
+    layers: impl Iterator<Item = tiled::Layer<'map>>,
+    layer_index: &mut usize,
+    inherited: &InheritedStyle,
 ) {
-    // If we have new map entities add them to the changed_maps list.
-    for _new_map in new_maps.iter() {
-        for map_handle in map_query.iter_mut() {
-            if let Some(tiled_map) = maps.get(map_handle) {
-                for (tileset_index, tileset) in tiled_map.map.tilesets().iter().enumerate() {
-                    let Some(tilemap_texture) = tiled_map.tilemap_textures.get(&tileset_index)
-                    else {
-                        log::warn!("Skipped creating layer with missing tilemap textures.");
-                        continue;
-                    };
+    for layer in layers {
+        let offset = layer_offset(&layer, tiled_map.settings.scale);
+        let visibility = if layer.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        let mut layer_entity = parent.spawn(SpatialBundle {
+            transform: Transform::from_translation(offset.extend(0.0)),
+            visibility,
+            ..Default::default()
+        });
+        layer_entity
+            .insert(Name::new(layer.name.clone()))
+            .insert(TiledLayerStyle::from(&layer))
+            .insert(tiled_map.with_project_types(
+                layer.user_type.as_deref(),
+                TiledProperties::from(&layer.properties),
+            ));
 
-                    let tile_size = TilemapTileSize {
-                        width: tileset.tile_width as f32,
-                        height: tileset.tile_height as f32,
-                    };
+        if let tiled::LayerType::Group(group_layer) = layer.layer_type() {
+            layer_entity.with_children(|parent| {
+                spawn_layers(
+                    parent,
+                    tiled_map,
+                    group_layer.layers(),
+                    layer_index,
+                    &inherited.with_group(&layer, tiled_map.settings.scale),
+                );
+            });
+            continue;
+        }
 
-                    let tile_spacing = TilemapSpacing {
-                        x: tileset.spacing as f32,
-                        y: tileset.spacing as f32,
-                    };
+        let index = *layer_index;
+        *layer_index += 1;
+        let color = inherited.color_of(&layer);
 
-                    let tilemap_size = TilemapSize {
-                        columns: tileset.columns as usize,
-                        rows: (tileset.tilecount / tileset.columns) as usize,
-                        width: tiled_map.map.width as usize,
-                        height: tiled_map.map.height as usize,
-                    };
+        let parallax = tiled_map.layer_parallax(&layer, inherited);
+        if let Some(parallax) = parallax {
+            layer_entity.insert(parallax);
+        }
 
-                    // Once materials have been created/added we need to then create the layers.
-                    for (layer_index, layer) in tiled_map.map.layers().enumerate() {
-                        let tiled::LayerType::Objects(object_layer) = layer.layer_type() else {
-                            continue;
-                        };
-
-                        let texture_atlas = TextureAtlas::from_grid(
-                            tilemap_texture.clone(),
-                            vec2(tile_size.width, tile_size.height),
-                            tilemap_size.columns,
-                            tilemap_size.rows,
-                            Some(vec2(tile_spacing.x, tile_spacing.y)),
-                            None,
-                        );
-
-                        let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-                        for object in object_layer.objects() {
-                            // A sptite based tile that needs rendering
-                            let Some(layer_tile_data) = object.tile_data() else {
-                                continue;
-                            };
-
-                            let sprite_index = layer_tile_data.id();
-
-                            let object_point = Point::from_tiled_object(
-                                &tilemap_size,
-                                &tile_size,
-                                SCALE,
-                                object.x,
-                                object.y,
-                            );
-
-                            let sprite = TextureAtlasSprite::new(sprite_index as usize);
-
-                            let sprite_bundle = SpriteSheetBundle {
-                                texture_atlas: texture_atlas_handle.clone(),
-                                transform: Transform {
-                                    scale: Vec3::splat(SCALE),
-                                    translation: Vec3::new(
-                                        object_point.x,
-                                        object_point.y,
-                                        layer_index as f32,
-                                    ),
-                                    ..Default::default()
-                                },
-                                sprite,
-                                ..Default::default()
-                            };
-
-                            let layer_name = layer.name.clone();
-
-                            let name = if object.name.is_empty() {
-                                None
-                            } else {
-                                Some(object.name.clone())
-                            };
-
-                            let class = if object.user_type.is_empty() {
-                                None
-                            } else {
-                                Some(object.user_type.clone())
-                            };
-
-                            commands
-                                .spawn(sprite_bundle)
-                                .insert(Name::new(layer_name))
-                                .insert(TiledObject { name, class })
-                                .insert(tile_size.scaled(SCALE));
-                        }
-                    }
+        layer_entity.with_children(|parent| match layer.layer_type() {
+            tiled::LayerType::Tiles(tile_layer) => {
+                spawn_tiles(parent, tiled_map, &layer, &tile_layer, index, color);
+                spawn_collideables(parent, tiled_map, &tile_layer, index);
+            }
+            tiled::LayerType::Objects(object_layer) => {
+                spawn_object_sprites(parent, tiled_map, &layer, &object_layer, index, color);
+                let map_offset = inherited.offset + offset;
+                spawn_object_shapes(parent, tiled_map, &layer, &object_layer, index, map_offset);
+            }
+            tiled::LayerType::Image(image_layer) => {
+                let parallax_factor = parallax.map_or(Vec2::ONE, |parallax| parallax.factor);
+                spawn_image(
+                    parent,
+                    tiled_map,
+                    &layer,
+                    &image_layer,
+                    index,
+                    color,
+                    parallax_factor,
+                );
+            }
+            tiled::LayerType::Group(_) => (),
+        });
+    }
+}
+
+fn spawn_tiles(
+    parent: &mut ChildBuilder,
+    tiled_map: &TiledMap,
+    layer: &tiled::Layer,
+    tile_layer: &TileLayer,
+    layer_index: usize,
+    color: Color,
+) {
+    for (tileset_index, tileset) in tiled_map.map.tilesets().iter().enumerate() {
+        let Some(texture_atlas_handle) = tiled_map.texture_atlases.get(&tileset_index) else {
+            log::warn!("Skipped creating layer with missing tilemap textures.");
+            continue;
+        };
+
+        let tile_size = tileset_tile_size(tiled_map, tileset);
+
+        let tilemap_size = tiled_map.tilemap_size(Some(tileset));
+
+        let Some(layer_tiles) = build_tiles(
+            tiled_map,
+            tile_layer,
+            &tilemap_size,
+            &tile_size,
+            tileset_index,
+            layer_index,
+            color,
+        ) else {
+            log::info!(
+                "No tiles for layer {} [{}]",
+                layer.name.clone(),
+                layer_index,
+            );
+            continue;
+        };
+
+        let mut tilemap = TileMap::default();
+        tilemap.set_tiles(layer_tiles.tiles);
+
+        let map_origin = Point::get_map_origin(&tilemap_size, &tile_size, &tiled_map.settings);
+        let tile_offset = tileset_offset(tileset, tiled_map.settings.scale);
+        let scale = Vec3::splat(tiled_map.settings.scale);
+        let translation = Vec3::new(
+            map_origin.x + tile_offset.x,
+            map_origin.y + tile_offset.y,
+            tiled_map.settings.layer_z(layer_index, 0.0),
+        );
+
+        let tilemap_bundle = TileMapBundle {
+            tilemap,
+            texture_atlas: texture_atlas_handle.clone(),
+            transform: Transform {
+                scale,
+                translation,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut tilemap_entity = parent.spawn(tilemap_bundle);
+        tilemap_entity
+            .insert(Name::new(tileset.name.clone()))
+            .insert(tile_size.scaled(tiled_map.settings.scale));
+
+        if !layer_tiles.animated_tiles.is_empty() {
+            tilemap_entity.insert(layer_tiles.animated_tiles);
+        }
+
+        // Sprite tiles are placed in the tilemap's space, where each tile is its size in pixels,
+        // and ordered within the layer above the rest of the tilemap.
+        tilemap_entity.with_children(|parent| {
+            for sprite_tile in layer_tiles.sprite_tiles {
+                let mut sprite = TextureAtlasSprite::new(sprite_tile.sprite_index as usize);
+                sprite.color = color;
+                sprite.flip_x = sprite_tile.orientation.flip_x;
+                sprite.flip_y = sprite_tile.orientation.flip_y;
+
+                let z = tiled_map
+                    .settings
+                    .layer_z(layer_index, sprite_tile.translation.z)
+                    - translation.z;
+                let translation = sprite_tile
+                    .translation
+                    .truncate()
+                    .extend(z / tiled_map.settings.scale);
+
+                let mut tile_entity = parent.spawn(SpriteSheetBundle {
+                    texture_atlas: texture_atlas_handle.clone(),
+                    transform: Transform {
+                        translation,
+                        rotation: sprite_tile.orientation.rotation(),
+                        ..Default::default()
+                    },
+                    sprite,
+                    ..Default::default()
+                });
+                tile_entity.insert(Name::new("Sprite tile"));
+
+                if let Some(animation) = sprite_tile.animation {
+                    tile_entity.insert(animation);
                 }
             }
+        });
+    }
+}
+
+fn spawn_image(
+    parent: &mut ChildBuilder,
+    tiled_map: &TiledMap,
+    layer: &tiled::Layer,
+    image_layer: &tiled::ImageLayer,
+    layer_index: usize,
+    color: Color,
+    parallax_factor: Vec2,
+) {
+    let (Some(texture), Some(image)) = (
+        tiled_map.image_layer_textures.get(&layer.id()),
+        &image_layer.image,
+    ) else {
+        log::info!("Skipped image layer {} without an image.", layer.name);
+        return;
+    };
+
+    let tilemap_size = tiled_map.tilemap_size(None);
+    let map_rect = tilemap_size.grid.pixel_rect();
+    let (map_start, map_size) = (map_rect.min, map_rect.size());
+    let image_size = vec2(image.width as f32, image.height as f32);
+    let offset = vec2(layer.offset_x, layer.offset_y);
+    let attributes = tiled_map.attributes.layer(layer.id());
+
+    // The layer entity is moved by the layer offset, so the first copy of the image is at its
+    // origin. A repeated image fills the map along that axis from there. A layer with a parallax
+    // factor slides by up to the map's size as the camera crosses the map, so it is filled that
+    // much further either side.
+    let copies =
+        |repeat: bool, start: f32, offset: f32, image_size: f32, map_size: f32, factor: f32| {
+            if !repeat || image_size <= 0.0 {
+                return 0..1;
+            }
+
+            let slide = map_size * (1.0 - factor).abs();
+            let first = ((start - slide - offset) / image_size).floor() as i32;
+            let last = ((start + map_size + slide - offset) / image_size).ceil() as i32;
+            first..last.max(first + 1)
+        };
+
+    let columns = copies(
+        attributes.repeat_x,
+        map_start.x,
+        offset.x,
+        image_size.x,
+        map_size.x,
+        parallax_factor.x,
+    );
+    let rows = copies(
+        attributes.repeat_y,
+        map_start.y,
+        offset.y,
+        image_size.y,
+        map_size.y,
+        parallax_factor.y,
+    );
+
+    for column in columns {
+        for row in rows.clone() {
+            let position = vec2(column as f32, row as f32) * image_size;
+            let point =
+                Point::from_tiled_pixel(&tilemap_size, &tiled_map.settings, position.x, position.y);
+
+            parent
+                .spawn(SpriteBundle {
+                    texture: texture.clone(),
+                    sprite: Sprite {
+                        color,
+                        anchor: Anchor::TopLeft,
+                        ..Default::default()
+                    },
+                    transform: Transform {
+                        scale: Vec3::splat(tiled_map.settings.scale),
+                        translation: Vec3::new(
+                            point.x,
+                            point.y,
+                            tiled_map.settings.layer_z(layer_index, 0.0),
+                        ),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(Name::new("Image"));
         }
     }
 }
 
-pub fn process_map_object_shapes(
-    mut commands: Commands,
-    mut map_query: Query<&Handle<TiledMap>>,
-    maps: Res<Assets<TiledMap>>,
-    new_maps: Query<&Handle<TiledMap>, Added<Handle<TiledMap>>>,
+fn spawn_collideables(
+    parent: &mut ChildBuilder,
+    tiled_map: &TiledMap,
+    tile_layer: &TileLayer,
+    layer_index: usize,
 ) {
-    // If we have new map entities add them to the changed_maps list.
-    for _new_map in new_maps.iter() {
-        for map_handle in map_query.iter_mut() {
-            if let Some(tiled_map) = maps.get(map_handle) {
-                for tileset in tiled_map.map.tilesets().iter() {
-                    let tile_size = TilemapTileSize {
-                        width: tileset.tile_width as f32,
-                        height: tileset.tile_height as f32,
-                    };
+    for (tileset_index, tileset) in tiled_map.map.tilesets().iter().enumerate() {
+        let tile_size = tileset_tile_size(tiled_map, tileset);
 
-                    let tilemap_size = TilemapSize {
-                        columns: tileset.columns as usize,
-                        rows: (tileset.tilecount / tileset.columns) as usize,
-                        width: tiled_map.map.width as usize,
-                        height: tiled_map.map.height as usize,
-                    };
+        let tilemap_size = tiled_map.tilemap_size(Some(tileset));
 
-                    // Once materials have been created/added we need to then create the layers.
-                    for (layer_index, layer) in tiled_map.map.layers().enumerate() {
-                        let tiled::LayerType::Objects(object_layer) = layer.layer_type() else {
-                            continue;
-                        };
-
-                        for object in object_layer.objects() {
-                            // A sptite based tile that needs rendering
-                            if object.tile_data().is_some() {
-                                continue;
-                            };
-
-                            // TODO: Support more shapes than just Rectangle
-                            let tiled::ObjectShape::Rect { width, height } = object.shape else {
-                                log::info!("Found non rectangle, skipping");
-                                continue;
-                            };
-
-                            let object_point = Point::from_tiled_object_shape(
-                                &tilemap_size,
-                                &tile_size,
-                                SCALE,
-                                object.x,
-                                object.y,
-                                width,
-                                height,
-                            );
-
-                            let translation =
-                                Vec3::new(object_point.x, object_point.y, layer_index as f32);
-
-                            let object_size = TilemapTileSize { width, height }.scaled(SCALE);
-
-                            let name = if object.name.is_empty() {
-                                None
-                            } else {
-                                Some(object.name.clone())
-                            };
-
-                            let class = if object.user_type.is_empty() {
-                                None
-                            } else {
-                                Some(object.user_type.clone())
-                            };
-
-                            let tiled_shape = TiledShape {
-                                collision_point: object_point,
-                                name,
-                                class,
-                            };
-
-                            commands
-                                .spawn(SpriteBundle {
-                                    sprite: Sprite {
-                                        color: Color::rgba(1., 1., 1., 0.5),
-                                        custom_size: Some(Vec2::new(width, height)),
-                                        ..Default::default()
-                                    },
-                                    transform: Transform {
-                                        scale: Vec3::splat(SCALE),
-                                        translation,
-                                        ..Default::default()
-                                    },
-                                    // Set to visible if you want to see the portal
-                                    // areas for debugging
-                                    visibility: Visibility::Hidden,
-                                    ..Default::default()
-                                })
-                                .insert(tiled_shape)
-                                .insert(object_size)
-                                .insert(Name::new(object.user_type.clone()));
-                        }
-                    }
+        let collideables = build_collideables(
+            &tilemap_size,
+            tiled_map,
+            tile_layer,
+            tileset_index,
+            layer_index,
+        );
+
+        let scaled_tile_size = tile_size.scaled(tiled_map.settings.scale);
+
+        for (collideable, properties) in collideables {
+            let color = Color::rgba(0.25, 0.25, 0.75, 0.5);
+            let custom_size = Some(Vec2::new(scaled_tile_size.width, scaled_tile_size.height));
+            // Drawn over the rest of the layer, for when they are shown
+            let translation = Vec3 {
+                x: collideable.collision_point.x,
+                y: collideable.collision_point.y,
+                z: tiled_map.settings.layer_z(layer_index, 1.0),
+            };
+
+            parent
+                // The sprite bundle just renders a transparent colored
+                // rectangle showing where this non sprite object exists
+                // e.g a collision shape
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size,
+                        ..Default::default()
+                    },
+                    transform: Transform {
+                        translation,
+                        ..Default::default()
+                    },
+                    // Set to visible if you want to see the collision
+                    // areas for debugging
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                })
+                .insert(Name::new("Collideable"))
+                .insert(scaled_tile_size)
+                .insert(collideable)
+                .insert(properties);
+        }
+    }
+}
+
+fn spawn_object_sprites(
+    parent: &mut ChildBuilder,
+    tiled_map: &TiledMap,
+    layer: &tiled::Layer,
+    object_layer: &tiled::ObjectLayer,
+    layer_index: usize,
+    color: Color,
+) {
+    for (tileset_index, tileset) in tiled_map.map.tilesets().iter().enumerate() {
+        let Some(texture_atlas_handle) = tiled_map.texture_atlases.get(&tileset_index) else {
+            log::warn!("Skipped creating layer with missing tilemap textures.");
+            continue;
+        };
+
+        let tile_size = tileset_tile_size(tiled_map, tileset);
+
+        let tilemap_size = tiled_map.tilemap_size(Some(tileset));
+
+        let tile_offset = tileset_offset(tileset, tiled_map.settings.scale);
+        let y_sort = tiled_map.layer_y_sort(layer, layer_index);
+        let draw_order = tiled_map.attributes.layer(layer.id()).draw_order;
+        let object_count = object_layer.objects().len();
+
+        for (object_index, object) in object_layer.objects().enumerate() {
+            // A sptite based tile that needs rendering
+            let Some(layer_tile_data) = object.tile_data() else {
+                continue;
+            };
+
+            if layer_tile_data.tileset_location() != &tiled::TilesetLocation::Map(tileset_index) {
+                continue;
+            }
+
+            let sprite_index = tiled_map.sprite_index(tileset_index, layer_tile_data.id());
+
+            // Tile objects are drawn at the size they were given in Tiled, which for image
+            // collections differs from tile to tile.
+            let object_size = match object.shape {
+                tiled::ObjectShape::Rect { width, height } if width > 0.0 && height > 0.0 => {
+                    TilemapTileSize { width, height }
                 }
+                _ => tile_size,
+            };
+
+            let object_point =
+                Point::from_tiled_object(&tilemap_size, &tiled_map.settings, object.x, object.y);
+
+            let orientation = TileOrientation::new(
+                layer_tile_data.flip_h,
+                layer_tile_data.flip_v,
+                layer_tile_data.flip_d,
+            );
+
+            let size = vec2(object_size.width, object_size.height);
+            // Tiled places tile objects by the point of their image given by their tileset's
+            // object alignment.
+            let anchor = tilemap_size
+                .grid
+                .tile_object_anchor(tiled_map.attributes.object_alignment(tileset_index));
+
+            // Flipping a tile object diagonally turns its image within its bounds, so it is
+            // rotated around its center rather than its anchor.
+            let rotation = orientation.rotation();
+            let center = -anchor.as_vec() * size * tiled_map.settings.scale;
+            let rotation_offset = center - (rotation * center.extend(0.0)).truncate();
+
+            let mut sprite = TextureAtlasSprite::new(sprite_index as usize);
+            sprite.color = color;
+            sprite.flip_x = orientation.flip_x;
+            sprite.flip_y = orientation.flip_y;
+            sprite.custom_size = Some(size);
+            sprite.anchor = anchor;
+
+            let sprite_bundle = SpriteSheetBundle {
+                texture_atlas: texture_atlas_handle.clone(),
+                transform: Transform {
+                    scale: Vec3::splat(tiled_map.settings.scale),
+                    translation: Vec3::new(
+                        object_point.x + tile_offset.x + rotation_offset.x,
+                        object_point.y + tile_offset.y + rotation_offset.y,
+                        tiled_map.settings.layer_z(
+                            layer_index,
+                            object_depth(
+                                draw_order,
+                                &tilemap_size.grid,
+                                &object,
+                                object_index,
+                                object_count,
+                            ),
+                        ),
+                    ),
+                    rotation,
+                },
+                sprite,
+                ..Default::default()
+            };
+
+            let layer_name = layer.name.clone();
+
+            let name = if object.name.is_empty() {
+                None
+            } else {
+                Some(object.name.clone())
+            };
+
+            let class = if object.user_type.is_empty() {
+                None
+            } else {
+                Some(object.user_type.clone())
+            };
+
+            let tile = object.get_tile().and_then(|tile| tile.get_tile());
+
+            // Tile objects take on the properties of their tile, unless they override them.
+            let mut properties = TiledProperties::from(&object.properties);
+            if let Some(tile) = &tile {
+                properties.inherit(&TiledProperties::from(&tile.properties));
+            }
+            let properties = tiled_map.with_project_types(class.as_deref(), properties);
+
+            let mut object_entity = parent.spawn(sprite_bundle);
+            object_entity
+                .insert(Name::new(layer_name))
+                .insert(TiledObject {
+                    id: object.id(),
+                    name,
+                    class,
+                })
+                .insert(properties)
+                .insert(object_size.scaled(tiled_map.settings.scale));
+
+            if let Some(animation) =
+                tile.and_then(|tile| TiledAnimation::from_tile(tiled_map, tileset_index, &tile))
+            {
+                object_entity.insert(animation);
             }
+
+            if let Some(y_sort) = y_sort {
+                object_entity.insert(y_sort);
+            }
+        }
+    }
+}
+
+fn spawn_object_shapes(
+    parent: &mut ChildBuilder,
+    tiled_map: &TiledMap,
+    layer: &tiled::Layer,
+    object_layer: &tiled::ObjectLayer,
+    layer_index: usize,
+    map_offset: Vec2,
+) {
+    // Shapes don't belong to a tileset, so they are placed on the map's grid alone.
+    let tilemap_size = tiled_map.tilemap_size(None);
+    let scale = tiled_map.settings.scale;
+    let draw_order = tiled_map.attributes.layer(layer.id()).draw_order;
+    let object_count = object_layer.objects().len();
+    // Isometric maps draw rectangles and ellipses projected onto the grid, as diamonds and
+    // skewed ellipses, so their geometry is given as the outline Tiled draws.
+    let is_isometric = tilemap_size.grid.orientation == tiled::Orientation::Isometric;
+
+    for (object_index, object) in object_layer.objects().enumerate() {
+        // A sptite based tile that needs rendering
+        if object.tile_data().is_some() {
+            continue;
+        };
+
+        // Tiled rotates objects clockwise around their position. Points within the object are
+        // rotated along with it before they are placed on the map.
+        let rotation = Vec2::from_angle(object.rotation.to_radians());
+        let to_layer = |offset: Vec2| {
+            let position = vec2(object.x, object.y) + rotation.rotate(offset);
+            let point = Point::from_tiled_object(
+                &tilemap_size,
+                &tiled_map.settings,
+                position.x,
+                position.y,
+            );
+            vec2(point.x, point.y)
+        };
+        let to_map = |offset: Vec2| to_layer(offset) + map_offset;
+        // Anticlockwise in world space, as y points up
+        let angle = -object.rotation.to_radians();
+
+        // Rectangles and ellipses are positioned from the top left corner of their bounds, where
+        // as the sprite drawn for them is centered, so we need to adjust for that
+        let (position, size, geometry) = match &object.shape {
+            tiled::ObjectShape::Rect { width, height } => {
+                let size = vec2(*width, *height);
+                let geometry = if is_isometric {
+                    let corners = [Vec2::ZERO, vec2(size.x, 0.0), size, vec2(0.0, size.y)];
+                    TiledShapeGeometry::Polygon {
+                        vertices: corners.into_iter().map(to_map).collect(),
+                    }
+                } else {
+                    TiledShapeGeometry::Rect {
+                        size: size * scale,
+                        rotation: angle,
+                    }
+                };
+                (to_layer(size / 2.0), size, geometry)
+            }
+            tiled::ObjectShape::Ellipse { width, height } => {
+                let size = vec2(*width, *height);
+                let geometry = if is_isometric {
+                    const SEGMENTS: usize = 32;
+                    let vertices = (0..SEGMENTS)
+                        .map(|segment| {
+                            let angle = segment as f32 / SEGMENTS as f32 * TAU;
+                            to_map(size / 2.0 * (Vec2::ONE + Vec2::from_angle(angle)))
+                        })
+                        .collect();
+                    TiledShapeGeometry::Polygon { vertices }
+                } else {
+                    TiledShapeGeometry::Ellipse {
+                        radii: size / 2.0 * scale,
+                        rotation: angle,
+                    }
+                };
+                (to_layer(size / 2.0), size, geometry)
+            }
+            tiled::ObjectShape::Point(..) => {
+                (to_layer(Vec2::ZERO), Vec2::ZERO, TiledShapeGeometry::Point)
+            }
+            tiled::ObjectShape::Polygon { points } => {
+                let vertices = points.iter().map(|&(x, y)| to_map(vec2(x, y))).collect();
+                (
+                    to_layer(Vec2::ZERO),
+                    Vec2::ZERO,
+                    TiledShapeGeometry::Polygon { vertices },
+                )
+            }
+            tiled::ObjectShape::Polyline { points } => {
+                let vertices = points.iter().map(|&(x, y)| to_map(vec2(x, y))).collect();
+                (
+                    to_layer(Vec2::ZERO),
+                    Vec2::ZERO,
+                    TiledShapeGeometry::Polyline { vertices },
+                )
+            }
+            tiled::ObjectShape::Text { .. } => {
+                (to_layer(Vec2::ZERO), Vec2::ZERO, TiledShapeGeometry::Text)
+            }
+        };
+
+        let translation = Vec3::new(
+            position.x,
+            position.y,
+            tiled_map.settings.layer_z(
+                layer_index,
+                object_depth(
+                    draw_order,
+                    &tilemap_size.grid,
+                    &object,
+                    object_index,
+                    object_count,
+                ),
+            ),
+        );
+
+        let object_size = TilemapTileSize {
+            width: size.x,
+            height: size.y,
         }
+        .scaled(scale);
+
+        let name = if object.name.is_empty() {
+            None
+        } else {
+            Some(object.name.clone())
+        };
+
+        let class = if object.user_type.is_empty() {
+            None
+        } else {
+            Some(object.user_type.clone())
+        };
+
+        let properties = tiled_map
+            .with_project_types(class.as_deref(), TiledProperties::from(&object.properties));
+
+        let map_position = position + map_offset;
+        let tiled_shape = TiledShape {
+            id: object.id(),
+            collision_point: Point {
+                x: map_position.x,
+                y: map_position.y,
+            },
+            name,
+            class,
+            geometry,
+        };
+
+        parent
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1., 1., 1., 0.5),
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                transform: Transform {
+                    scale: Vec3::splat(scale),
+                    translation,
+                    rotation: Quat::from_rotation_z(angle),
+                },
+                // Set to visible if you want to see the portal
+                // areas for debugging
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            })
+            .insert(tiled_shape)
+            .insert(properties)
+            .insert(object_size)
+            .insert(Name::new(object.user_type.clone()));
+    }
+}
+
+/// How far through an object layer the object at `object_index` is drawn, following the layer's
+/// draw order
+fn object_depth(
+    draw_order: attributes::DrawOrder,
+    grid: &MapGrid,
+    object: &tiled::Object,
+    object_index: usize,
+    object_count: usize,
+) -> f32 {
+    match draw_order {
+        attributes::DrawOrder::TopDown => grid.object_depth(vec2(object.x, object.y)),
+        attributes::DrawOrder::Index => object_index as f32 / object_count as f32,
+    }
+}
+
+/// The number of rows of tiles in a tileset's image
+fn tileset_rows(tileset: &tiled::Tileset) -> usize {
+    if tileset.columns == 0 {
+        return 0;
     }
+
+    tileset.tilecount.div_ceil(tileset.columns) as usize
+}
+
+/// The texture atlas of a tileset's image. Tiled places the first tile after the margin, and
+/// leaves the spacing between each tile.
+fn tileset_texture_atlas(texture: Handle<Image>, tileset: &tiled::Tileset) -> TextureAtlas {
+    let tile_spacing = TilemapSpacing {
+        x: tileset.spacing as f32,
+        y: tileset.spacing as f32,
+    };
+    let margin = tileset.margin as f32;
+
+    TextureAtlas::from_grid(
+        texture,
+        vec2(tileset.tile_width as f32, tileset.tile_height as f32),
+        tileset.columns as usize,
+        tileset_rows(tileset),
+        Some(vec2(tile_spacing.x, tile_spacing.y)),
+        Some(vec2(margin, margin)),
+    )
+}
+
+/// The size of the cells a tileset's tiles are laid out in. The tiles of an image collection
+/// differ in size, so they are laid out on the map's grid.
+fn tileset_tile_size(tiled_map: &TiledMap, tileset: &tiled::Tileset) -> TilemapTileSize {
+    if tileset.image.is_none() {
+        return TilemapTileSize {
+            width: tiled_map.map.tile_width as f32,
+            height: tiled_map.map.tile_height as f32,
+        };
+    }
+
+    TilemapTileSize {
+        width: tileset.tile_width as f32,
+        height: tileset.tile_height as f32,
+    }
+}
+
+/// The offset a tileset's tiles are drawn at, in world space. Tiled's offset points down.
+fn tileset_offset(tileset: &tiled::Tileset, scale: f32) -> Vec2 {
+    vec2(tileset.offset_x as f32, -tileset.offset_y as f32) * scale
+}
+
+/// The tiles of a layer that use one tileset
+#[derive(Default)]
+struct LayerTiles {
+    tiles: Vec<(IVec3, Option<Tile>)>,
+    animated_tiles: TiledAnimatedTiles,
+    /// Tiles the tilemap can't draw, which are spawned as sprites instead: those flipped
+    /// diagonally, those of image collections, as they aren't all the size of a tile, and those
+    /// of maps that aren't laid out in rows and columns
+    sprite_tiles: Vec<SpriteTile>,
+}
+
+struct SpriteTile {
+    /// The position of the center of the tile in the tilemap's space, and how far through the
+    /// layer it is drawn
+    translation: Vec3,
+    sprite_index: u32,
+    orientation: TileOrientation,
+    animation: Option<TiledAnimation>,
 }
 
 fn build_tiles(
+    tiled_map: &TiledMap,
     tile_layer: &TileLayer,
     tilemap_size: &TilemapSize,
+    tile_size: &TilemapTileSize,
     tileset_index: usize,
     layer_index: usize,
-) -> Option<Vec<(IVec3, Option<Tile>)>> {
+    color: Color,
+) -> Option<LayerTiles> {
     log::info!("Building tile tiles for layer {}", layer_index);
 
-    let tiled::TileLayer::Finite(layer_data) = tile_layer else {
-        log::info!(
-            "Skipping layer {} because only finite layers are supported.",
-            layer_index,
-        );
-        return None;
-    };
-
-    let mut tiles: Vec<(IVec3, Option<Tile>)> = vec![];
+    let mut layer_tiles = LayerTiles::default();
 
     for x in 0..tilemap_size.width {
         for y in 0..tilemap_size.height {
             let point = Point::from_tiled_tile(tilemap_size, x, y);
 
-            let layer_tile = match layer_data.get_tile(point.x as i32, point.y as i32) {
+            let layer_tile = match tile_layer.get_tile(
+                tilemap_size.origin.x + point.x as i32,
+                tilemap_size.origin.y + point.y as i32,
+            ) {
                 Some(t) => t,
                 None => {
                     continue;
@@ -592,66 +1525,112 @@ fn build_tiles(
                 continue;
             }
 
-            let layer_tile_data = match layer_data.get_tile_data(point.x as i32, point.y as i32) {
-                Some(d) => d,
-                None => {
-                    continue;
-                }
-            };
+            let orientation =
+                TileOrientation::new(layer_tile.flip_h, layer_tile.flip_v, layer_tile.flip_d);
+
+            let position = ivec3(x as i32, y as i32, 0);
+            let sprite_index = tiled_map.sprite_index(tileset_index, layer_tile.id());
+            let tile = layer_tile.get_tile();
+            let animation = tile
+                .as_ref()
+                .and_then(|tile| TiledAnimation::from_tile(tiled_map, tileset_index, tile));
+            let image_size = tile
+                .as_ref()
+                .and_then(|tile| tile.image.as_ref())
+                .map(|image| vec2(image.width as f32, image.height as f32));
+
+            if orientation.is_rotated()
+                || image_size.is_some()
+                || !tilemap_size.grid.is_orthogonal()
+            {
+                let size = image_size.unwrap_or(vec2(tile_size.width, tile_size.height));
+                let tile = tilemap_size.origin + ivec2(point.x as i32, point.y as i32);
+
+                layer_tiles.sprite_tiles.push(SpriteTile {
+                    translation: sprite_tile_translation(tilemap_size, tile_size, tile, size),
+                    sprite_index,
+                    orientation,
+                    animation,
+                });
+                continue;
+            }
 
-            let flags = if layer_tile_data.flip_v && layer_tile_data.flip_d {
-                TileFlags::FLIP_X | TileFlags::FLIP_Y
-            } else if layer_tile_data.flip_v {
-                TileFlags::FLIP_Y
-            } else if layer_tile_data.flip_d {
-                TileFlags::FLIP_X
-            } else {
-                TileFlags::default()
+            let tile = Tile {
+                sprite_index,
+                color,
+                flags: orientation.tile_flags(),
             };
 
-            tiles.push((
-                ivec3(x as i32, y as i32, layer_index as i32),
-                Some(Tile {
-                    sprite_index: layer_tile.id(),
-                    flags,
-                    ..Default::default()
-                }),
-            ));
+            if let Some(animation) = animation {
+                layer_tiles
+                    .animated_tiles
+                    .push(position, tile.clone(), animation);
+            }
+
+            layer_tiles.tiles.push((position, Some(tile)));
         }
     }
 
-    Some(tiles)
+    if layer_tiles.tiles.is_empty() && layer_tiles.sprite_tiles.is_empty() {
+        return None;
+    }
+
+    Some(layer_tiles)
 }
 
-fn build_collideables(
+/// Where a sprite tile is drawn in its tilemap's space, and how far through the layer. Like
+/// Tiled, images are aligned to the bottom left of their cell, so those bigger than a cell stick
+/// out above and to the right, over the tiles drawn before them.
+fn sprite_tile_translation(
     tilemap_size: &TilemapSize,
     tile_size: &TilemapTileSize,
+    tile: IVec2,
+    size: Vec2,
+) -> Vec3 {
+    let grid = &tilemap_size.grid;
+    let map_size = grid.pixel_size();
+    let cell = grid.cell_position(tile);
+    let center = vec2(
+        cell.x + size.x / 2.0,
+        cell.y + grid.tile_size.y - size.y / 2.0,
+    );
+
+    // The tilemap's origin is the center of the map's bottom left tile, with y pointing up.
+    let x = center.x - tile_size.width / 2.0;
+    let y = map_size.y - tile_size.height / 2.0 - center.y;
+
+    vec3(x, y, grid.tile_depth(tile))
+}
+
+fn build_collideables(
+    tilemap_size: &TilemapSize,
+    tiled_map: &TiledMap,
     tile_layer: &TileLayer,
+    tileset_index: usize,
     layer_index: usize,
-) -> Option<Vec<TiledCollideable>> {
+) -> Vec<(TiledCollideable, TiledProperties)> {
     log::info!("Building collideables for layer {}", layer_index);
 
-    let tiled::TileLayer::Finite(layer_data) = tile_layer else {
-        log::info!(
-            "Skipping layer {} because only finite layers are supported.",
-            layer_index,
-        );
-        return None;
-    };
-
-    let mut collideables: Vec<TiledCollideable> = vec![];
+    let mut collideables: Vec<(TiledCollideable, TiledProperties)> = vec![];
 
     for x in 0..tilemap_size.width {
         for y in 0..tilemap_size.height {
             let tile_point = Point::from_tiled_tile(tilemap_size, x, y);
 
-            let layer_tile = match layer_data.get_tile(tile_point.x as i32, tile_point.y as i32) {
+            let layer_tile = match tile_layer.get_tile(
+                tilemap_size.origin.x + tile_point.x as i32,
+                tilemap_size.origin.y + tile_point.y as i32,
+            ) {
                 Some(t) => t,
                 None => {
                     continue;
                 }
             };
 
+            if tileset_index != layer_tile.tileset_index() {
+                continue;
+            }
+
             // Extract obstacles. We are keeping this simple and only dealing
             // with Rect (rectangle) collision shapes.
             let Some(tile) = layer_tile.get_tile() else {
@@ -674,8 +1653,7 @@ fn build_collideables(
                 {
                     let collision_point = Point::from_tiled_collision(
                         tilemap_size,
-                        tile_size,
-                        SCALE,
+                        &tiled_map.settings,
                         tile_point.x as i32,
                         tile_point.y as i32,
                     );
@@ -686,7 +1664,12 @@ fn build_collideables(
                         name: tile.user_type.clone(),
                     };
 
-                    collideables.push(collideable);
+                    let properties = tiled_map.with_project_types(
+                        tile.user_type.as_deref(),
+                        TiledProperties::from(&tile.properties),
+                    );
+
+                    collideables.push((collideable, properties));
                 }
             }
         }
@@ -696,7 +1679,54 @@ fn build_collideables(
         log::info!("No collideables found for layer {}", layer_index);
     }
 
-    Some(collideables)
+    collideables
+}
+
+/// How a tile is drawn, from the flip flags Tiled stores with it. Tiled flips a tile diagonally
+/// first, then horizontally, then vertically. A diagonal flip can't be made by flipping the
+/// texture alone, so it becomes a quarter turn combined with at most one flip.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TileOrientation {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// The number of anticlockwise quarter turns, applied after flipping
+    pub quarter_turns: i32,
+}
+
+impl TileOrientation {
+    pub fn new(flip_h: bool, flip_v: bool, flip_d: bool) -> Self {
+        let (flip_x, flip_y, quarter_turns) = match (flip_d, flip_h, flip_v) {
+            (false, flip_h, flip_v) => (flip_h, flip_v, 0),
+            (true, false, false) => (true, false, 1),
+            // Rotated clockwise in Tiled
+            (true, true, false) => (false, false, -1),
+            // Rotated anticlockwise in Tiled
+            (true, false, true) => (false, false, 1),
+            (true, true, true) => (false, true, 1),
+        };
+
+        Self {
+            flip_x,
+            flip_y,
+            quarter_turns,
+        }
+    }
+
+    pub fn is_rotated(&self) -> bool {
+        self.quarter_turns != 0
+    }
+
+    /// The tilemap flags for the orientation, which can't hold a rotation
+    pub fn tile_flags(&self) -> TileFlags {
+        let mut flags = TileFlags::default();
+        flags.set(TileFlags::FLIP_X, self.flip_x);
+        flags.set(TileFlags::FLIP_Y, self.flip_y);
+        flags
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_z(self.quarter_turns as f32 * FRAC_PI_2)
+    }
 }
 
 #[derive(Reflect, Component, Copy, Clone, Default, Debug, InspectorOptions)]
@@ -706,34 +1736,34 @@ pub struct Point {
 }
 
 impl Point {
+    /// The world position of the center of a tile's cell, from its position relative to the
+    /// map's top left tile
     pub fn from_tiled_collision(
         tilemap_size: &TilemapSize,
-        tile_size: &TilemapTileSize,
-        scale: f32,
+        settings: &TiledLoaderSettings,
         x: i32,
         y: i32,
     ) -> Self {
-        let x = (x as f32 * tile_size.scaled(scale).width)
-            - ((tilemap_size.width as f32 * tile_size.scaled(scale).width) / 2.0)
-            + (tile_size.scaled(scale).width / 2.0);
-
-        let y = -((y as f32 * tile_size.scaled(scale).height)
-            - ((tilemap_size.height as f32 * tile_size.scaled(scale).height) / 2.0))
-            - (tile_size.scaled(scale).height / 2.0);
+        let center = tilemap_size
+            .grid
+            .cell_center(tilemap_size.origin + ivec2(x, y));
 
-        Self { x, y }
+        Self::from_map_position(tilemap_size, settings, center)
     }
 
-    /// Calculate the origin point of the map for placing in the center of the screen
+    /// Calculate the origin point of the map, the center of its bottom left tile, so that the
+    /// map is placed at the anchor and offset given in the settings
     pub fn get_map_origin(
         tilemap_size: &TilemapSize,
         tile_size: &TilemapTileSize,
-        scale: f32,
+        settings: &TiledLoaderSettings,
     ) -> Self {
-        let x = -((tilemap_size.width as f32 * tile_size.scaled(scale).width) / 2.0)
-            + ((tile_size.scaled(scale).width) / 2.0);
-        let y = -((tilemap_size.height as f32 * tile_size.scaled(scale).height) / 2.0)
-            + ((tile_size.scaled(scale).height) / 2.0);
+        let corner = settings.map_corner(tilemap_size);
+        let map_height = tilemap_size.grid.pixel_size().y * settings.scale;
+        let tile_size = tile_size.scaled(settings.scale);
+
+        let x = corner.x + (tile_size.width / 2.0);
+        let y = corner.y - map_height + (tile_size.height / 2.0);
 
         Self { x, y }
     }
@@ -753,37 +1783,38 @@ impl Point {
     /// Transform TMX object coords into bevy coords.
     pub fn from_tiled_object(
         tilemap_size: &TilemapSize,
-        tile_size: &TilemapTileSize,
-        scale: f32,
+        settings: &TiledLoaderSettings,
         x: f32,
         y: f32,
     ) -> Self {
-        let x = (x * scale) - ((tilemap_size.width as f32 * tile_size.scaled(scale).width) / 2.0);
-
-        let y =
-            -((y * scale) - ((tilemap_size.height as f32 * tile_size.scaled(scale).height) / 2.0));
+        let position = tilemap_size.grid.object_position(vec2(x, y));
 
-        Self { x, y }
+        Self::from_map_position(tilemap_size, settings, position)
     }
 
-    /// Transform TMX object shape coords into bevy coords.
-    pub fn from_tiled_object_shape(
+    /// Transform a point where Tiled draws it, such as the position of an image layer, into bevy
+    /// coords.
+    pub fn from_tiled_pixel(
         tilemap_size: &TilemapSize,
-        tile_size: &TilemapTileSize,
-        scale: f32,
+        settings: &TiledLoaderSettings,
         x: f32,
         y: f32,
-        width: f32,
-        height: f32,
     ) -> Self {
-        let x = (x * scale) - ((tilemap_size.width as f32 * tile_size.scaled(scale).width) / 2.0)
-            // this is because shape objects have the x in the center, where as regular objects are left, so we need to adjust for that
-            + (width * 1.5);
+        let position = tilemap_size.grid.pixel_position(vec2(x, y));
+
+        Self::from_map_position(tilemap_size, settings, position)
+    }
+
+    /// Transform a position on the map, from its top left corner, into bevy coords.
+    fn from_map_position(
+        tilemap_size: &TilemapSize,
+        settings: &TiledLoaderSettings,
+        position: Vec2,
+    ) -> Self {
+        let corner = settings.map_corner(tilemap_size);
 
-        let y = -((y * scale)
-            - ((tilemap_size.height as f32 * tile_size.scaled(scale).height) / 2.0))
-            // this is because shape objects have the y in the center, where as regular objects are top, so we need to adjust for that
-            - (height * 1.5);
+        let x = corner.x + (position.x * settings.scale);
+        let y = corner.y - (position.y * settings.scale);
 
         Self { x, y }
     }
@@ -798,13 +1829,613 @@ pub struct TiledCollideable {
 
 #[derive(Component, Debug)]
 pub struct TiledShape {
+    /// The Tiled object ID, unique within the map
+    pub id: u32,
+    /// The position of the shape in the space of the map entity: the center of rectangles and
+    /// ellipses, and the origin of the other shapes
     pub collision_point: Point,
     pub name: Option<String>,
     pub class: Option<String>,
+    pub geometry: TiledShapeGeometry,
+}
+
+/// The geometry of a shape object, in the space of the map entity. It includes the map's loader
+/// settings and the offsets of the shape's layer and groups, but not the map entity's transform or
+/// the scrolling of parallax layers: [TiledShape::world_geometry] places it in the world.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TiledShapeGeometry {
+    /// A rectangle centered on the shape's position, turned anticlockwise by `rotation` radians.
+    /// The rectangles of isometric maps are polygons.
+    Rect { size: Vec2, rotation: f32 },
+    /// An ellipse centered on the shape's position, turned anticlockwise by `rotation` radians.
+    /// The ellipses of isometric maps are polygons following their outline.
+    Ellipse { radii: Vec2, rotation: f32 },
+    /// A single point at the shape's position
+    Point,
+    /// A closed area, with its vertices in the space of the map entity
+    Polygon { vertices: Vec<Vec2> },
+    /// An open path, with its vertices in the space of the map entity
+    Polyline { vertices: Vec<Vec2> },
+    /// A text object, placed from its top left corner
+    Text,
+}
+
+impl TiledShape {
+    /// The shape's position in the world, given the [GlobalTransform] of its map entity
+    pub fn world_position(&self, map_transform: &GlobalTransform) -> Vec2 {
+        let point = vec3(self.collision_point.x, self.collision_point.y, 0.0);
+
+        map_transform.transform_point(point).truncate()
+    }
+
+    /// The shape's geometry in the world, given the [GlobalTransform] of its map entity. The map's
+    /// scale and its rotation around z are applied to rectangles and ellipses, which stay exact
+    /// unless the map is both rotated and scaled unevenly.
+    pub fn world_geometry(&self, map_transform: &GlobalTransform) -> TiledShapeGeometry {
+        let (scale, map_rotation, _) = map_transform.to_scale_rotation_translation();
+        let (angle, _, _) = map_rotation.to_euler(EulerRot::ZYX);
+        let to_world = |vertices: &[Vec2]| {
+            vertices
+                .iter()
+                .map(|vertex| map_transform.transform_point(vertex.extend(0.0)).truncate())
+                .collect()
+        };
+
+        match &self.geometry {
+            TiledShapeGeometry::Rect { size, rotation } => TiledShapeGeometry::Rect {
+                size: *size * scale.truncate(),
+                rotation: rotation + angle,
+            },
+            TiledShapeGeometry::Ellipse { radii, rotation } => TiledShapeGeometry::Ellipse {
+                radii: *radii * scale.truncate(),
+                rotation: rotation + angle,
+            },
+            TiledShapeGeometry::Polygon { vertices } => TiledShapeGeometry::Polygon {
+                vertices: to_world(vertices),
+            },
+            TiledShapeGeometry::Polyline { vertices } => TiledShapeGeometry::Polyline {
+                vertices: to_world(vertices),
+            },
+            TiledShapeGeometry::Point => TiledShapeGeometry::Point,
+            TiledShapeGeometry::Text => TiledShapeGeometry::Text,
+        }
+    }
 }
 
 #[derive(Component, Debug)]
 pub struct TiledObject {
+    /// The Tiled object ID, unique within the map
+    pub id: u32,
     pub name: Option<String>,
     pub class: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetPlugin,
+        prelude::{App, With},
+        transform::TransformPlugin,
+        MinimalPlugins,
+    };
+
+    use super::*;
+
+    /// A map parsed from a TMX document with embedded tilesets, the way the loader builds it
+    pub(crate) fn map_from_tmx(tmx: &str, settings: TiledLoaderSettings) -> TiledMap {
+        let path = PathBuf::from("map.tmx");
+        let attributes = attributes::MapAttributes::from_xml(tmx.as_bytes(), |_| None).unwrap();
+        let map = tiled::Loader::with_cache_and_reader(
+            tiled::DefaultResourceCache::new(),
+            BytesResourceReader {
+                files: HashMap::from([(path.clone(), tmx.as_bytes().into())]),
+            },
+        )
+        .load_tmx_map(path)
+        .unwrap();
+        let tile_bounds = tile_bounds(&map);
+        // Tilesets are given atlases without textures, which is enough to spawn their tiles.
+        let texture_atlases = map
+            .tilesets()
+            .iter()
+            .enumerate()
+            .filter(|(_, tileset)| tileset.image.is_some())
+            .map(|(tileset_index, _)| (tileset_index, Handle::default()))
+            .collect();
+
+        TiledMap {
+            map,
+            settings,
+            project: None,
+            texture_atlases,
+            image_layer_textures: HashMap::default(),
+            attributes,
+            tile_bounds,
+            tile_image_offsets: HashMap::default(),
+        }
+    }
+
+    /// An app with the map plugin and an entity for a map parsed from a TMX document, which spawns
+    /// on the next updates
+    pub(crate) fn app_with_map(
+        tmx: &str,
+        settings: TiledLoaderSettings,
+    ) -> (App, Handle<TiledMap>, Entity) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            TransformPlugin,
+            TiledMapPlugin,
+        ));
+
+        let handle = app
+            .world
+            .resource_mut::<Assets<TiledMap>>()
+            .add(map_from_tmx(tmx, settings));
+        let map_entity = app
+            .world
+            .spawn(TiledMapBundle {
+                tiled_map: handle.clone(),
+                ..Default::default()
+            })
+            .id();
+
+        (app, handle, map_entity)
+    }
+
+    /// The position and geometry of each shape spawned in the app, by object ID
+    fn spawned_shapes(app: &mut App) -> HashMap<u32, (Vec2, TiledShapeGeometry)> {
+        app.world
+            .query::<&TiledShape>()
+            .iter(&app.world)
+            .map(|shape| {
+                let position = vec2(shape.collision_point.x, shape.collision_point.y);
+                (shape.id, (position, shape.geometry.clone()))
+            })
+            .collect()
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-4),
+            "{actual} is not {expected}"
+        );
+    }
+
+    fn assert_vertices(geometry: &TiledShapeGeometry, expected: &[Vec2]) {
+        let (TiledShapeGeometry::Polygon { vertices } | TiledShapeGeometry::Polyline { vertices }) =
+            geometry
+        else {
+            panic!("{geometry:?} has no vertices");
+        };
+
+        assert_eq!(vertices.len(), expected.len());
+        for (&vertex, &expected) in vertices.iter().zip(expected) {
+            assert_near(vertex, expected);
+        }
+    }
+
+    fn top_left(scale: f32) -> TiledLoaderSettings {
+        TiledLoaderSettings {
+            scale,
+            anchor: TiledMapAnchor::TopLeft,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spawns_the_geometry_of_every_shape_on_orthogonal_maps() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="4" height="4" tilewidth="16" tileheight="16" infinite="0">
+ <objectgroup id="1" name="Shapes">
+  <object id="1" x="16" y="16" width="32" height="16"/>
+  <object id="2" x="16" y="16" width="32" height="16" rotation="90"/>
+  <object id="3" x="0" y="32" width="16" height="8"><ellipse/></object>
+  <object id="4" x="8" y="8"><point/></object>
+  <object id="5" x="32" y="32"><polygon points="0,0 16,0 0,16"/></object>
+  <object id="6" x="32" y="32"><polyline points="0,0 16,16"/></object>
+  <object id="7" x="4" y="4" width="32" height="16"><text>Sign</text></object>
+ </objectgroup>
+</map>
+"#;
+        let (mut app, _, _) = app_with_map(tmx, top_left(2.0));
+        app.update();
+        app.update();
+
+        let shapes = spawned_shapes(&mut app);
+        assert_eq!(shapes.len(), 7);
+
+        // Rectangles and ellipses are placed at their center, and sized by the map's scale.
+        let (position, geometry) = &shapes[&1];
+        assert_near(*position, vec2(64.0, -48.0));
+        assert_eq!(
+            *geometry,
+            TiledShapeGeometry::Rect {
+                size: vec2(64.0, 32.0),
+                rotation: 0.0
+            }
+        );
+
+        // Tiled turns them clockwise around their top left corner.
+        let (position, geometry) = &shapes[&2];
+        assert_near(*position, vec2(16.0, -64.0));
+        assert_eq!(
+            *geometry,
+            TiledShapeGeometry::Rect {
+                size: vec2(64.0, 32.0),
+                rotation: -FRAC_PI_2
+            }
+        );
+
+        let (position, geometry) = &shapes[&3];
+        assert_near(*position, vec2(16.0, -72.0));
+        assert_eq!(
+            *geometry,
+            TiledShapeGeometry::Ellipse {
+                radii: vec2(16.0, 8.0),
+                rotation: 0.0
+            }
+        );
+
+        let (position, geometry) = &shapes[&4];
+        assert_near(*position, vec2(16.0, -16.0));
+        assert_eq!(*geometry, TiledShapeGeometry::Point);
+
+        let (position, geometry) = &shapes[&5];
+        assert_near(*position, vec2(64.0, -64.0));
+        assert!(matches!(geometry, TiledShapeGeometry::Polygon { .. }));
+        assert_vertices(
+            geometry,
+            &[vec2(64.0, -64.0), vec2(96.0, -64.0), vec2(64.0, -96.0)],
+        );
+
+        let (position, geometry) = &shapes[&6];
+        assert_near(*position, vec2(64.0, -64.0));
+        assert!(matches!(geometry, TiledShapeGeometry::Polyline { .. }));
+        assert_vertices(geometry, &[vec2(64.0, -64.0), vec2(96.0, -96.0)]);
+
+        let (position, geometry) = &shapes[&7];
+        assert_near(*position, vec2(8.0, -8.0));
+        assert_eq!(*geometry, TiledShapeGeometry::Text);
+    }
+
+    #[test]
+    fn spawns_the_outlines_of_rectangles_and_ellipses_on_isometric_maps() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="isometric" renderorder="right-down" width="4" height="4" tilewidth="32" tileheight="16" infinite="0">
+ <objectgroup id="1" name="Shapes">
+  <object id="1" x="0" y="0" width="16" height="16"/>
+  <object id="2" x="16" y="16" width="16" height="16" rotation="90"/>
+  <object id="3" x="0" y="0" width="16" height="16"><ellipse/></object>
+  <object id="4" x="16" y="0"><point/></object>
+  <object id="5" x="16" y="0"><polygon points="0,0 16,0 0,16"/></object>
+  <object id="6" x="16" y="0"><polyline points="0,0 0,16"/></object>
+ </objectgroup>
+</map>
+"#;
+        let (mut app, _, _) = app_with_map(tmx, top_left(1.0));
+        app.update();
+        app.update();
+
+        let shapes = spawned_shapes(&mut app);
+        assert_eq!(shapes.len(), 6);
+
+        // Rectangles are the diamonds of the tiles they cover.
+        let (position, geometry) = &shapes[&1];
+        assert_near(*position, vec2(64.0, -8.0));
+        assert!(matches!(geometry, TiledShapeGeometry::Polygon { .. }));
+        assert_vertices(
+            geometry,
+            &[
+                vec2(64.0, 0.0),
+                vec2(80.0, -8.0),
+                vec2(64.0, -16.0),
+                vec2(48.0, -8.0),
+            ],
+        );
+
+        // Rotating turns the rectangle on the grid, around its top corner.
+        let (position, geometry) = &shapes[&2];
+        assert_near(*position, vec2(48.0, -16.0));
+        assert_vertices(
+            geometry,
+            &[
+                vec2(64.0, -16.0),
+                vec2(48.0, -24.0),
+                vec2(32.0, -16.0),
+                vec2(48.0, -8.0),
+            ],
+        );
+
+        // Ellipses are outlined from their right, going round through their bottom.
+        let (position, geometry) = &shapes[&3];
+        assert_near(*position, vec2(64.0, -8.0));
+        let TiledShapeGeometry::Polygon { vertices } = geometry else {
+            panic!("{geometry:?} is not a polygon");
+        };
+        assert_eq!(vertices.len(), 32);
+        assert_near(vertices[0], vec2(72.0, -12.0));
+        assert_near(vertices[8], vec2(56.0, -12.0));
+        assert_near(vertices[16], vec2(56.0, -4.0));
+        assert_near(vertices[24], vec2(72.0, -4.0));
+
+        let (position, geometry) = &shapes[&4];
+        assert_near(*position, vec2(80.0, -8.0));
+        assert_eq!(*geometry, TiledShapeGeometry::Point);
+
+        let (position, geometry) = &shapes[&5];
+        assert_near(*position, vec2(80.0, -8.0));
+        assert!(matches!(geometry, TiledShapeGeometry::Polygon { .. }));
+        assert_vertices(
+            geometry,
+            &[vec2(80.0, -8.0), vec2(96.0, -16.0), vec2(64.0, -16.0)],
+        );
+
+        let (position, geometry) = &shapes[&6];
+        assert_near(*position, vec2(80.0, -8.0));
+        assert!(matches!(geometry, TiledShapeGeometry::Polyline { .. }));
+        assert_vertices(geometry, &[vec2(80.0, -8.0), vec2(64.0, -16.0)]);
+    }
+
+    #[test]
+    fn shapes_are_placed_in_the_world_by_the_map_transform() {
+        let shape = |geometry| TiledShape {
+            id: 1,
+            collision_point: Point { x: 10.0, y: 0.0 },
+            name: None,
+            class: None,
+            geometry,
+        };
+        let map_transform = GlobalTransform::from(Transform {
+            translation: vec3(100.0, 50.0, 0.0),
+            rotation: Quat::from_rotation_z(FRAC_PI_2),
+            scale: Vec3::splat(2.0),
+        });
+
+        let rect = shape(TiledShapeGeometry::Rect {
+            size: vec2(4.0, 2.0),
+            rotation: 0.5,
+        });
+        assert_near(rect.world_position(&map_transform), vec2(100.0, 70.0));
+        let TiledShapeGeometry::Rect { size, rotation } = rect.world_geometry(&map_transform)
+        else {
+            panic!("rectangles stay rectangles");
+        };
+        assert_near(size, vec2(8.0, 4.0));
+        assert!((rotation - (0.5 + FRAC_PI_2)).abs() < 1e-5);
+
+        let ellipse = shape(TiledShapeGeometry::Ellipse {
+            radii: vec2(3.0, 1.0),
+            rotation: 0.0,
+        });
+        let TiledShapeGeometry::Ellipse { radii, rotation } =
+            ellipse.world_geometry(&map_transform)
+        else {
+            panic!("ellipses stay ellipses");
+        };
+        assert_near(radii, vec2(6.0, 2.0));
+        assert!((rotation - FRAC_PI_2).abs() < 1e-5);
+
+        let polygon = shape(TiledShapeGeometry::Polygon {
+            vertices: vec![vec2(10.0, 0.0), vec2(10.0, 5.0)],
+        });
+        assert_vertices(
+            &polygon.world_geometry(&map_transform),
+            &[vec2(100.0, 70.0), vec2(90.0, 70.0)],
+        );
+
+        let polyline = shape(TiledShapeGeometry::Polyline {
+            vertices: vec![vec2(0.0, 0.0), vec2(1.0, 1.0)],
+        });
+        assert_vertices(
+            &polyline.world_geometry(&map_transform),
+            &[vec2(100.0, 50.0), vec2(98.0, 52.0)],
+        );
+
+        let point = shape(TiledShapeGeometry::Point);
+        assert_eq!(
+            point.world_geometry(&map_transform),
+            TiledShapeGeometry::Point
+        );
+    }
+
+    /// A 3 by 3 map filled with tiles flipped diagonally, so even orthogonal tiles are spawned as
+    /// sprites, with a tile object at each of the given object coordinates
+    fn tile_map_tmx(attributes: &str, tile_size: (u32, u32), objects: &[(f32, f32)]) -> String {
+        let (width, height) = tile_size;
+        let objects: String = objects
+            .iter()
+            .enumerate()
+            .map(|(index, (x, y))| {
+                format!(
+                    r#"<object id="{}" gid="1" x="{x}" y="{y}" width="{width}" height="{height}"/>"#,
+                    index + 1
+                )
+            })
+            .collect();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" {attributes} renderorder="right-down" width="3" height="3" tilewidth="{width}" tileheight="{height}" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="{width}" tileheight="{height}" tilecount="1" columns="1">
+  <image source="tiles.png" width="{width}" height="{height}"/>
+ </tileset>
+ <layer id="1" name="Tiles" width="3" height="3">
+  <data encoding="csv">536870913,536870913,536870913,536870913,536870913,536870913,536870913,536870913,536870913</data>
+ </layer>
+ <objectgroup id="2" name="Objects">{objects}</objectgroup>
+</map>
+"#
+        )
+    }
+
+    /// Checks the world helpers against where the map's tiles and tile objects are spawned, with
+    /// the map entity moved away from the origin
+    fn assert_world_helpers_match_spawned_entities(tmx: &str, objects: &[(f32, f32)]) {
+        let settings = TiledLoaderSettings {
+            scale: 2.0,
+            ..Default::default()
+        };
+        let (mut app, handle, map_entity) = app_with_map(tmx, settings);
+        let map_translation = vec3(100.0, 50.0, 0.0);
+        app.world
+            .entity_mut(map_entity)
+            .insert(Transform::from_translation(map_translation));
+        app.update();
+        app.update();
+        app.update();
+
+        let tiled_map = app
+            .world
+            .resource::<Assets<TiledMap>>()
+            .get(&handle)
+            .unwrap();
+        let offset = map_translation.truncate();
+        let expected_tiles: Vec<Vec2> = (0..3)
+            .flat_map(|y| (0..3).map(move |x| (x, y)))
+            .map(|(x, y)| offset + tiled_map.tile_to_world(x, y))
+            .collect();
+        let expected_objects: Vec<(u32, Vec2)> = objects
+            .iter()
+            .enumerate()
+            .map(|(index, &(x, y))| (index as u32 + 1, offset + tiled_map.object_to_world(x, y)))
+            .collect();
+
+        let tiles: Vec<Vec2> = app
+            .world
+            .query_filtered::<&GlobalTransform, With<TextureAtlasSprite>>()
+            .iter(&app.world)
+            .map(|transform| transform.translation().truncate())
+            .collect();
+        assert_eq!(tiles.len(), expected_tiles.len() + expected_objects.len());
+        for expected in expected_tiles {
+            assert!(
+                tiles.iter().any(|tile| tile.abs_diff_eq(expected, 1e-3)),
+                "no tile at {expected} in {tiles:?}"
+            );
+        }
+
+        let spawned_objects: HashMap<u32, Vec2> = app
+            .world
+            .query::<(&TiledObject, &GlobalTransform)>()
+            .iter(&app.world)
+            .map(|(object, transform)| (object.id, transform.translation().truncate()))
+            .collect();
+        for (id, expected) in expected_objects {
+            assert_near(spawned_objects[&id], expected);
+        }
+    }
+
+    #[test]
+    fn world_helpers_match_orthogonal_maps() {
+        let objects = [(0.0, 16.0), (24.0, 40.0)];
+        let tmx = tile_map_tmx(r#"orientation="orthogonal""#, (16, 16), &objects);
+
+        assert_world_helpers_match_spawned_entities(&tmx, &objects);
+    }
+
+    #[test]
+    fn world_helpers_match_isometric_maps() {
+        let objects = [(0.0, 0.0), (16.0, 8.0), (48.0, 32.0)];
+        let tmx = tile_map_tmx(r#"orientation="isometric""#, (32, 16), &objects);
+
+        assert_world_helpers_match_spawned_entities(&tmx, &objects);
+    }
+
+    #[test]
+    fn world_helpers_match_staggered_maps() {
+        let objects = [(0.0, 16.0), (40.0, 28.0)];
+        let tmx = tile_map_tmx(
+            r#"orientation="staggered" staggeraxis="y" staggerindex="odd""#,
+            (32, 16),
+            &objects,
+        );
+
+        assert_world_helpers_match_spawned_entities(&tmx, &objects);
+    }
+
+    #[test]
+    fn world_helpers_match_hexagonal_maps() {
+        let objects = [(0.0, 28.0), (50.0, 60.0)];
+        let pointy = tile_map_tmx(
+            r#"orientation="hexagonal" hexsidelength="14" staggeraxis="y" staggerindex="odd""#,
+            (32, 28),
+            &objects,
+        );
+        let flat = tile_map_tmx(
+            r#"orientation="hexagonal" hexsidelength="16" staggeraxis="x" staggerindex="even""#,
+            (32, 28),
+            &objects,
+        );
+
+        assert_world_helpers_match_spawned_entities(&pointy, &objects);
+        assert_world_helpers_match_spawned_entities(&flat, &objects);
+    }
+
+    #[test]
+    fn tile_orientation_covers_every_flip_combination() {
+        // (flip_d, flip_h, flip_v) => (flip_x, flip_y, quarter_turns)
+        let cases = [
+            ((false, false, false), (false, false, 0)),
+            ((false, true, false), (true, false, 0)),
+            ((false, false, true), (false, true, 0)),
+            ((false, true, true), (true, true, 0)),
+            ((true, false, false), (true, false, 1)),
+            ((true, true, false), (false, false, -1)),
+            ((true, false, true), (false, false, 1)),
+            ((true, true, true), (false, true, 1)),
+        ];
+
+        for ((flip_d, flip_h, flip_v), (flip_x, flip_y, quarter_turns)) in cases {
+            assert_eq!(
+                TileOrientation::new(flip_h, flip_v, flip_d),
+                TileOrientation {
+                    flip_x,
+                    flip_y,
+                    quarter_turns,
+                },
+                "flip_d: {flip_d}, flip_h: {flip_h}, flip_v: {flip_v}"
+            );
+        }
+    }
+
+    #[test]
+    fn tile_orientation_draws_pixels_where_tiled_does() {
+        // A pixel of the tile's image, relative to its center, with y pointing up
+        let pixel = vec2(3.0, 1.0);
+
+        for flags in 0..8 {
+            let (flip_d, flip_h, flip_v) = (flags & 4 != 0, flags & 2 != 0, flags & 1 != 0);
+
+            // Tiled flips the tile diagonally, swapping its top right and bottom left corners,
+            // then horizontally, then vertically.
+            let mut expected = pixel;
+            if flip_d {
+                expected = vec2(-expected.y, -expected.x);
+            }
+            if flip_h {
+                expected.x = -expected.x;
+            }
+            if flip_v {
+                expected.y = -expected.y;
+            }
+
+            // The sprite flips its texture, then is rotated.
+            let orientation = TileOrientation::new(flip_h, flip_v, flip_d);
+            let mut flipped = pixel;
+            if orientation.flip_x {
+                flipped.x = -flipped.x;
+            }
+            if orientation.flip_y {
+                flipped.y = -flipped.y;
+            }
+            let drawn = orientation.rotation() * flipped.extend(0.0);
+
+            assert!(
+                drawn.truncate().abs_diff_eq(expected, 1e-5),
+                "flip_d: {flip_d}, flip_h: {flip_h}, flip_v: {flip_v}: {drawn} != {expected}"
+            );
+        }
+    }
+}
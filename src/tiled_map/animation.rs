@@ -0,0 +1,133 @@
+//! Tile animations. Tiles with animation frames in their tileset cycle through them, both in tile
+//! layers and as tile objects. Every instance of an animated tile shows the same frame at the same
+//! time, the way Tiled previews them.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::{Component, IVec3, Query, Res},
+    reflect::Reflect,
+    render::color::Color,
+    sprite::TextureAtlasSprite,
+    time::Time,
+};
+use bevy_simple_tilemap::{Tile, TileMap};
+
+use super::TiledMap;
+
+/// TiledAnimation holds the frames of an animated tile object
+#[derive(Reflect, Component, Debug, Clone)]
+pub struct TiledAnimation {
+    pub frames: Vec<TiledAnimationFrame>,
+}
+
+#[derive(Reflect, Debug, Clone, Copy)]
+pub struct TiledAnimationFrame {
+    /// The index of the frame's tile in the tileset's texture atlas
+    pub sprite_index: u32,
+    pub duration: Duration,
+}
+
+impl TiledAnimation {
+    /// The animation of a tile from the map's tileset at `tileset_index`, if it has one
+    pub fn from_tile(
+        tiled_map: &TiledMap,
+        tileset_index: usize,
+        tile: &tiled::Tile,
+    ) -> Option<Self> {
+        let frames: Vec<_> = tile
+            .animation
+            .as_ref()?
+            .iter()
+            .map(|frame| TiledAnimationFrame {
+                sprite_index: tiled_map.sprite_index(tileset_index, frame.tile_id),
+                duration: Duration::from_millis(frame.duration as u64),
+            })
+            .collect();
+
+        if frames.iter().all(|frame| frame.duration.is_zero()) {
+            return None;
+        }
+
+        Some(Self { frames })
+    }
+
+    /// The sprite index of the frame shown `elapsed` time after the animation started. Animations
+    /// loop forever.
+    pub fn sprite_index(&self, elapsed: Duration) -> u32 {
+        let total: Duration = self.frames.iter().map(|frame| frame.duration).sum();
+        let mut remaining = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+
+        for frame in self.frames.iter() {
+            if remaining < frame.duration {
+                return frame.sprite_index;
+            }
+            remaining -= frame.duration;
+        }
+
+        self.frames[0].sprite_index
+    }
+}
+
+/// TiledAnimatedTiles holds the animated tiles of a tile layer's tilemap
+#[derive(Component, Debug, Clone, Default)]
+pub struct TiledAnimatedTiles {
+    tiles: Vec<AnimatedTile>,
+}
+
+#[derive(Debug, Clone)]
+struct AnimatedTile {
+    position: IVec3,
+    /// The tile as it is currently shown
+    tile: Tile,
+    animation: TiledAnimation,
+}
+
+impl TiledAnimatedTiles {
+    pub(crate) fn push(&mut self, position: IVec3, tile: Tile, animation: TiledAnimation) {
+        self.tiles.push(AnimatedTile {
+            position,
+            tile,
+            animation,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Draw the animated tiles with another colour from their next frame on
+    pub(crate) fn set_color(&mut self, color: Color) {
+        for animated_tile in self.tiles.iter_mut() {
+            animated_tile.tile.color = color;
+        }
+    }
+}
+
+/// Show the current frame of every animated tile in a tile layer.
+pub fn animate_tiles(time: Res<Time>, mut query: Query<(&mut TiledAnimatedTiles, &mut TileMap)>) {
+    for (mut animated_tiles, mut tilemap) in query.iter_mut() {
+        for animated_tile in animated_tiles.tiles.iter_mut() {
+            let sprite_index = animated_tile.animation.sprite_index(time.elapsed());
+
+            if animated_tile.tile.sprite_index != sprite_index {
+                animated_tile.tile.sprite_index = sprite_index;
+                tilemap.set_tile(animated_tile.position, Some(animated_tile.tile.clone()));
+            }
+        }
+    }
+}
+
+/// Show the current frame of every animated tile object.
+pub fn animate_sprites(
+    time: Res<Time>,
+    mut query: Query<(&TiledAnimation, &mut TextureAtlasSprite)>,
+) {
+    for (animation, mut sprite) in query.iter_mut() {
+        let sprite_index = animation.sprite_index(time.elapsed()) as usize;
+
+        if sprite.index != sprite_index {
+            sprite.index = sprite_index;
+        }
+    }
+}
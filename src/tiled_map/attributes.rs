@@ -0,0 +1,258 @@
+//! Attributes of a Tiled map that tiled doesn't parse, read straight from the map's XML.
+
+use std::collections::HashMap;
+
+use bevy::math::{vec2, Vec2};
+use xml::{attribute::OwnedAttribute, reader::XmlEvent, EventReader};
+
+/// The attributes tiled skips, for the map and each of its layers
+#[derive(Debug, Clone, Default)]
+pub struct MapAttributes {
+    /// The point of the map, in Tiled's pixels, at which the camera sees every layer where it is
+    /// placed, whatever its parallax factor
+    pub parallax_origin: Vec2,
+    /// The length of the flat sides of a hexagonal map's tiles
+    pub hex_side_length: f32,
+    /// The order the tiles of each tile layer are drawn in
+    pub render_order: RenderOrder,
+    /// The attributes of each layer, by layer ID
+    pub layers: HashMap<u32, LayerAttributes>,
+    /// The object alignment of each tileset, in the order the map lists them
+    pub object_alignments: Vec<ObjectAlignment>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayerAttributes {
+    /// The image of an image layer is repeated along the x axis
+    pub repeat_x: bool,
+    /// The image of an image layer is repeated along the y axis
+    pub repeat_y: bool,
+    /// The order the objects of an object layer are drawn in
+    pub draw_order: DrawOrder,
+}
+
+/// The order Tiled draws the tiles of a layer in, row by row from the given corner of the map.
+/// Tiled only follows it on orthogonal maps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderOrder {
+    #[default]
+    RightDown,
+    RightUp,
+    LeftDown,
+    LeftUp,
+}
+
+/// The order Tiled draws the objects of an object layer in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrawOrder {
+    /// Objects lower down the map are drawn over those above them
+    #[default]
+    TopDown,
+    /// Objects are drawn in the order they are listed in the layer
+    Index,
+}
+
+/// The point of a tile object's image that Tiled places at the object's position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObjectAlignment {
+    /// The default for the map's orientation: bottom left for orthogonal maps, bottom center for
+    /// isometric ones
+    #[default]
+    Unspecified,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ObjectAlignment {
+    fn from_attribute(value: Option<&str>) -> Self {
+        match value {
+            Some("topleft") => Self::TopLeft,
+            Some("top") => Self::Top,
+            Some("topright") => Self::TopRight,
+            Some("left") => Self::Left,
+            Some("center") => Self::Center,
+            Some("right") => Self::Right,
+            Some("bottomleft") => Self::BottomLeft,
+            Some("bottom") => Self::Bottom,
+            Some("bottomright") => Self::BottomRight,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+impl MapAttributes {
+    /// Read the attributes of a TMX document. The attributes of the external tilesets it uses are
+    /// read from the TSX documents `read_tileset` gives for their `source`.
+    pub fn from_xml<'a>(
+        bytes: &[u8],
+        read_tileset: impl Fn(&str) -> Option<&'a [u8]>,
+    ) -> Result<Self, xml::reader::Error> {
+        let mut map_attributes = Self::default();
+
+        for event in EventReader::new(bytes) {
+            let XmlEvent::StartElement {
+                name, attributes, ..
+            } = event?
+            else {
+                continue;
+            };
+
+            if name.local_name == "map" {
+                let number = |name| {
+                    attribute(&attributes, name)
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or_default()
+                };
+
+                map_attributes.parallax_origin =
+                    vec2(number("parallaxoriginx"), number("parallaxoriginy"));
+                map_attributes.hex_side_length = number("hexsidelength");
+                map_attributes.render_order = match attribute(&attributes, "renderorder") {
+                    Some("right-up") => RenderOrder::RightUp,
+                    Some("left-down") => RenderOrder::LeftDown,
+                    Some("left-up") => RenderOrder::LeftUp,
+                    _ => RenderOrder::RightDown,
+                };
+            }
+
+            if name.local_name == "tileset" {
+                let object_alignment = match attribute(&attributes, "source") {
+                    Some(source) => match read_tileset(source) {
+                        Some(tileset) => tileset_object_alignment(tileset)?,
+                        None => ObjectAlignment::Unspecified,
+                    },
+                    None => {
+                        ObjectAlignment::from_attribute(attribute(&attributes, "objectalignment"))
+                    }
+                };
+
+                map_attributes.object_alignments.push(object_alignment);
+            }
+
+            if let "layer" | "objectgroup" | "imagelayer" | "group" = name.local_name.as_str() {
+                let Some(id) = attribute(&attributes, "id").and_then(|id| id.parse().ok()) else {
+                    continue;
+                };
+
+                let layer_attributes = LayerAttributes {
+                    repeat_x: attribute(&attributes, "repeatx") == Some("1"),
+                    repeat_y: attribute(&attributes, "repeaty") == Some("1"),
+                    draw_order: match attribute(&attributes, "draworder") {
+                        Some("index") => DrawOrder::Index,
+                        _ => DrawOrder::TopDown,
+                    },
+                };
+
+                map_attributes.layers.insert(id, layer_attributes);
+            }
+        }
+
+        Ok(map_attributes)
+    }
+
+    /// The attributes of the layer with the given ID
+    pub fn layer(&self, id: u32) -> LayerAttributes {
+        self.layers.get(&id).copied().unwrap_or_default()
+    }
+
+    /// The object alignment of the tileset at the given index
+    pub fn object_alignment(&self, tileset_index: usize) -> ObjectAlignment {
+        self.object_alignments
+            .get(tileset_index)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// The object alignment of a TSX document's tileset
+fn tileset_object_alignment(bytes: &[u8]) -> Result<ObjectAlignment, xml::reader::Error> {
+    for event in EventReader::new(bytes) {
+        if let XmlEvent::StartElement {
+            name, attributes, ..
+        } = event?
+        {
+            if name.local_name == "tileset" {
+                return Ok(ObjectAlignment::from_attribute(attribute(
+                    &attributes,
+                    "objectalignment",
+                )));
+            }
+        }
+    }
+
+    Ok(ObjectAlignment::Unspecified)
+}
+
+/// The paths of the files a TMX, TSX or TX document references that tiled reads along with it:
+/// external tilesets and object templates. They are relative to the document's directory.
+pub fn referenced_files(bytes: &[u8]) -> Result<Vec<String>, xml::reader::Error> {
+    let mut files = vec![];
+
+    for event in EventReader::new(bytes) {
+        let XmlEvent::StartElement {
+            name, attributes, ..
+        } = event?
+        else {
+            continue;
+        };
+
+        let reference = match name.local_name.as_str() {
+            "tileset" => attribute(&attributes, "source"),
+            "object" => attribute(&attributes, "template"),
+            _ => None,
+        };
+
+        if let Some(reference) = reference {
+            files.push(reference.to_string());
+        }
+    }
+
+    Ok(files)
+}
+
+/// The value of the named attribute of an XML element
+pub(super) fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attr| attr.name.local_name == name)
+        .map(|attr| attr.value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_object_alignment_of_embedded_and_external_tilesets() {
+        let map = br#"<map orientation="orthogonal">
+ <tileset firstgid="1" name="embedded" objectalignment="center"/>
+ <tileset firstgid="10" source="tiles/external.tsx"/>
+ <tileset firstgid="20" name="default"/>
+ <tileset firstgid="30" source="missing.tsx"/>
+</map>"#;
+        let external = br#"<tileset name="external" objectalignment="topright"/>"#;
+
+        let attributes = MapAttributes::from_xml(map, |source| {
+            (source == "tiles/external.tsx").then_some(&external[..])
+        })
+        .unwrap();
+
+        assert_eq!(
+            attributes.object_alignments,
+            vec![
+                ObjectAlignment::Center,
+                ObjectAlignment::TopRight,
+                ObjectAlignment::Unspecified,
+                ObjectAlignment::Unspecified,
+            ]
+        );
+        assert_eq!(attributes.object_alignment(4), ObjectAlignment::Unspecified);
+    }
+}
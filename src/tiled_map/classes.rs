@@ -0,0 +1,218 @@
+//! Components built from the class and custom properties of Tiled objects. Game code registers a
+//! component type for a Tiled class with [TiledClassAppExt::register_tiled_class], and every
+//! object of that class is given the component as soon as it is spawned.
+
+use std::collections::HashMap;
+
+use bevy::{
+    ecs::system::EntityCommands,
+    log,
+    prelude::{Added, AnyOf, App, Commands, Component, Entity, Or, Query, Res, Resource},
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{TiledObject, TiledProperties, TiledShape};
+
+pub trait TiledClassAppExt {
+    /// Give every object spawned with the Tiled class `class` a `T` component, deserialized from
+    /// the object's custom properties. Several components can be registered for the same class.
+    fn register_tiled_class<T: Component + DeserializeOwned>(&mut self, class: &str) -> &mut Self;
+}
+
+impl TiledClassAppExt for App {
+    fn register_tiled_class<T: Component + DeserializeOwned>(&mut self, class: &str) -> &mut Self {
+        self.init_resource::<TiledClassRegistry>();
+        self.world
+            .resource_mut::<TiledClassRegistry>()
+            .0
+            .entry(class.to_string())
+            .or_default()
+            .push(ClassComponent {
+                type_name: std::any::type_name::<T>(),
+                insert: insert_component::<T>,
+            });
+        self
+    }
+}
+
+/// The components registered for each Tiled class
+#[derive(Resource, Default)]
+pub struct TiledClassRegistry(HashMap<String, Vec<ClassComponent>>);
+
+struct ClassComponent {
+    type_name: &'static str,
+    insert: fn(&mut EntityCommands, Value) -> Result<(), serde_json::Error>,
+}
+
+fn insert_component<T: Component + DeserializeOwned>(
+    entity: &mut EntityCommands,
+    properties: Value,
+) -> Result<(), serde_json::Error> {
+    let component = match serde_json::from_value::<T>(properties) {
+        Ok(component) => component,
+        // Unit structs can only be deserialized from null, whatever properties the object has.
+        Err(e) => serde_json::from_value::<T>(Value::Null).map_err(|_| e)?,
+    };
+
+    entity.insert(component);
+    Ok(())
+}
+
+/// Insert the registered components of newly spawned objects, based on their class.
+#[allow(clippy::type_complexity)]
+pub fn insert_class_components(
+    mut commands: Commands,
+    registry: Res<TiledClassRegistry>,
+    object_query: Query<
+        (Entity, AnyOf<(&TiledObject, &TiledShape)>, &TiledProperties),
+        Or<(Added<TiledObject>, Added<TiledShape>)>,
+    >,
+) {
+    for (entity, (tiled_object, tiled_shape), properties) in object_query.iter() {
+        let (id, name, class) = match (tiled_object, tiled_shape) {
+            (Some(tiled_object), _) => (tiled_object.id, &tiled_object.name, &tiled_object.class),
+            (_, Some(tiled_shape)) => (tiled_shape.id, &tiled_shape.name, &tiled_shape.class),
+            _ => continue,
+        };
+
+        let Some(components) = class.as_ref().and_then(|class| registry.0.get(class)) else {
+            continue;
+        };
+
+        let mut entity = commands.entity(entity);
+
+        for component in components {
+            if let Err(e) = (component.insert)(&mut entity, properties.to_json()) {
+                log::error!(
+                    "Could not create {} for object {} ({}): {}",
+                    component.type_name,
+                    id,
+                    name.as_deref().unwrap_or("unnamed"),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{App, Update};
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::tiled_map::TiledPropertyValue;
+
+    #[derive(Component, Deserialize, Debug, PartialEq)]
+    struct Door {
+        locked: bool,
+        key: String,
+    }
+
+    #[derive(Component, Deserialize, Debug, PartialEq)]
+    struct Marker;
+
+    #[derive(Component, Deserialize, Debug, PartialEq)]
+    struct Health(i32);
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.register_tiled_class::<Door>("Door")
+            .register_tiled_class::<Marker>("Door")
+            .register_tiled_class::<Health>("Door")
+            .add_systems(Update, insert_class_components);
+        app
+    }
+
+    fn spawn_object(
+        app: &mut App,
+        class: &str,
+        properties: &[(&str, TiledPropertyValue)],
+    ) -> Entity {
+        let properties = TiledProperties(
+            properties
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        );
+
+        app.world
+            .spawn((
+                TiledObject {
+                    id: 1,
+                    name: None,
+                    class: Some(class.to_string()),
+                },
+                properties,
+            ))
+            .id()
+    }
+
+    #[test]
+    fn inserts_components_from_properties() {
+        let mut app = app();
+        let door = spawn_object(
+            &mut app,
+            "Door",
+            &[
+                ("locked", TiledPropertyValue::Bool(true)),
+                ("key", TiledPropertyValue::String("red".to_string())),
+            ],
+        );
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Door>(door),
+            Some(&Door {
+                locked: true,
+                key: "red".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn inserts_unit_markers_on_objects_with_properties() {
+        let mut app = app();
+        let door = spawn_object(
+            &mut app,
+            "Door",
+            &[("locked", TiledPropertyValue::Bool(false))],
+        );
+        let empty = spawn_object(&mut app, "Door", &[]);
+
+        app.update();
+
+        assert_eq!(app.world.get::<Marker>(door), Some(&Marker));
+        assert_eq!(app.world.get::<Marker>(empty), Some(&Marker));
+    }
+
+    #[test]
+    fn skips_components_that_fail_to_deserialize() {
+        let mut app = app();
+        // Health can't be made from a map, and Door is missing its key, but the marker is still
+        // inserted.
+        let door = spawn_object(
+            &mut app,
+            "Door",
+            &[("locked", TiledPropertyValue::Bool(true))],
+        );
+
+        app.update();
+
+        assert!(app.world.get::<Door>(door).is_none());
+        assert!(app.world.get::<Health>(door).is_none());
+        assert_eq!(app.world.get::<Marker>(door), Some(&Marker));
+    }
+
+    #[test]
+    fn ignores_other_classes() {
+        let mut app = app();
+        let window = spawn_object(&mut app, "Window", &[]);
+
+        app.update();
+
+        assert!(app.world.get::<Marker>(window).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! Tilesets made from a collection of images, one per tile. Their images are packed into a single
+//! texture when the map loads, so their tiles can be drawn from a texture atlas like those of any
+//! other tileset.
+
+use std::cmp::Reverse;
+
+use bevy::{
+    asset::{AssetPath, LoadContext},
+    math::{uvec2, Rect},
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::Image,
+    },
+};
+
+use super::TiledAssetLoaderError;
+
+/// The images of an image collection tileset, packed into one texture
+pub struct PackedTileset {
+    pub image: Image,
+    /// The area of each tile's image within the packed texture
+    pub rects: Vec<(tiled::TileId, Rect)>,
+}
+
+/// Load the image of every tile in an image collection tileset and pack them together, tallest
+/// first, in rows of a roughly square texture.
+pub async fn pack_image_collection(
+    load_context: &mut LoadContext<'_>,
+    tileset: &tiled::Tileset,
+) -> Result<Option<PackedTileset>, TiledAssetLoaderError> {
+    let mut images = vec![];
+
+    for (tile_id, tile) in tileset.tiles() {
+        let Some(tile_image) = &tile.image else {
+            continue;
+        };
+
+        // Like a tileset image, the source is already relative to the assets/ directory.
+        let source = tile_image.source.to_string_lossy().to_string();
+        let loaded = load_context
+            .load_direct(AssetPath::from(tile_image.source.clone()))
+            .await?;
+
+        let image = loaded
+            .take::<Image>()
+            .and_then(|image| image.try_into_dynamic().ok())
+            .ok_or(TiledAssetLoaderError::TileImageFormat(source))?;
+
+        images.push((tile_id, image.to_rgba8()));
+    }
+
+    if images.is_empty() {
+        return Ok(None);
+    }
+
+    images.sort_by_key(|(_, image)| Reverse(image.height()));
+
+    let area: u32 = images
+        .iter()
+        .map(|(_, image)| image.width() * image.height())
+        .sum();
+    let widest = images
+        .iter()
+        .map(|(_, image)| image.width())
+        .max()
+        .unwrap_or_default();
+    let width = widest.max((area as f32).sqrt().ceil() as u32);
+
+    let mut positions = Vec::with_capacity(images.len());
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+
+    for (_, image) in images.iter() {
+        if x + image.width() > width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+
+        positions.push(uvec2(x, y));
+        x += image.width();
+        row_height = row_height.max(image.height());
+    }
+
+    let height = y + row_height;
+    let mut data = vec![0; (width * height * 4) as usize];
+    let mut rects = Vec::with_capacity(images.len());
+
+    for ((tile_id, image), position) in images.iter().zip(positions) {
+        let row_bytes = (image.width() * 4) as usize;
+
+        for (row, pixels) in image.as_raw().chunks_exact(row_bytes).enumerate() {
+            let start = (((position.y + row as u32) * width + position.x) * 4) as usize;
+            data[start..start + row_bytes].copy_from_slice(pixels);
+        }
+
+        let min = position.as_vec2();
+        let max = min + uvec2(image.width(), image.height()).as_vec2();
+        rects.push((*tile_id, Rect::from_corners(min, max)));
+    }
+
+    let image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+
+    Ok(Some(PackedTileset { image, rects }))
+}
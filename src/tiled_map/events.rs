@@ -0,0 +1,147 @@
+//! Lifecycle events for map entities, so that game code can react to a map's content being
+//! spawned without polling for it.
+
+use std::collections::HashMap;
+
+use bevy::prelude::{Changed, Entity, Event, EventWriter, Handle, Local, Query, RemovedComponents};
+
+use super::{TiledMap, TiledMapState};
+
+/// TiledMapEvent is sent as a map entity goes through loading, spawning and reloading
+#[derive(Event, Debug, Clone)]
+pub enum TiledMapEvent {
+    /// The map asset and all of its textures have finished loading, and its content is about to
+    /// be spawned
+    Loaded {
+        entity: Entity,
+        handle: Handle<TiledMap>,
+    },
+    /// The map content has been spawned as children of the map entity and can be queried. This is
+    /// sent every time the content is spawned, including after a reload.
+    Spawned {
+        entity: Entity,
+        handle: Handle<TiledMap>,
+    },
+    /// The map content has been despawned, either because the map entity was removed or because
+    /// its asset is being reloaded
+    Despawned {
+        entity: Entity,
+        handle: Handle<TiledMap>,
+    },
+    /// The map content has been spawned again after its asset was modified
+    Reloaded {
+        entity: Entity,
+        handle: Handle<TiledMap>,
+    },
+}
+
+/// Send [TiledMapEvent::Spawned] and [TiledMapEvent::Reloaded] once the spawned content exists,
+/// and [TiledMapEvent::Despawned] for map entities that have been removed.
+pub fn send_spawned_events(
+    mut spawned_maps: Local<HashMap<Entity, Handle<TiledMap>>>,
+    mut events: EventWriter<TiledMapEvent>,
+    map_query: Query<(Entity, &Handle<TiledMap>, &TiledMapState), Changed<TiledMapState>>,
+    mut removed_maps: RemovedComponents<Handle<TiledMap>>,
+) {
+    for (entity, handle, state) in map_query.iter() {
+        if *state != TiledMapState::Spawned {
+            continue;
+        }
+
+        let reloaded = spawned_maps.insert(entity, handle.clone()).is_some();
+
+        events.send(TiledMapEvent::Spawned {
+            entity,
+            handle: handle.clone(),
+        });
+
+        if reloaded {
+            events.send(TiledMapEvent::Reloaded {
+                entity,
+                handle: handle.clone(),
+            });
+        }
+    }
+
+    for entity in removed_maps.read() {
+        if let Some(handle) = spawned_maps.remove(&entity) {
+            events.send(TiledMapEvent::Despawned { entity, handle });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::event::ManualEventReader,
+        prelude::{App, Assets, Events},
+    };
+
+    use super::*;
+    use crate::tiled_map::{tests::app_with_map, TiledLoaderSettings};
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <objectgroup id="1" name="Objects"/>
+</map>
+"#;
+
+    /// The kind of each event sent since the last call, for the given map entity
+    fn read_events(
+        app: &App,
+        reader: &mut ManualEventReader<TiledMapEvent>,
+        map_entity: Entity,
+    ) -> Vec<&'static str> {
+        reader
+            .read(app.world.resource::<Events<TiledMapEvent>>())
+            .map(|event| match event {
+                TiledMapEvent::Loaded { entity, .. } => (entity, "Loaded"),
+                TiledMapEvent::Spawned { entity, .. } => (entity, "Spawned"),
+                TiledMapEvent::Despawned { entity, .. } => (entity, "Despawned"),
+                TiledMapEvent::Reloaded { entity, .. } => (entity, "Reloaded"),
+            })
+            .filter(|(entity, _)| **entity == map_entity)
+            .map(|(_, kind)| kind)
+            .collect()
+    }
+
+    fn update(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn events_follow_the_map_through_spawning_reloading_and_despawning() {
+        let (mut app, handle, map_entity) = app_with_map(TMX, TiledLoaderSettings::default());
+
+        let mut reader = ManualEventReader::default();
+
+        update(&mut app, 3);
+        assert_eq!(
+            read_events(&app, &mut reader, map_entity),
+            ["Loaded", "Spawned"]
+        );
+
+        // Modifying the asset reloads the map.
+        app.world
+            .resource_mut::<Assets<TiledMap>>()
+            .get_mut(&handle)
+            .unwrap()
+            .settings
+            .scale = 2.0;
+        update(&mut app, 3);
+        assert_eq!(
+            read_events(&app, &mut reader, map_entity),
+            ["Despawned", "Loaded", "Spawned", "Reloaded"]
+        );
+
+        // Nothing more is sent while the map stays as it is.
+        update(&mut app, 3);
+        assert!(read_events(&app, &mut reader, map_entity).is_empty());
+
+        app.world.despawn(map_entity);
+        update(&mut app, 2);
+        assert_eq!(read_events(&app, &mut reader, map_entity), ["Despawned"]);
+    }
+}
@@ -0,0 +1,437 @@
+//! The layout of a map's tiles. Orthogonal maps place their tiles in rows and columns, isometric
+//! maps place them in diamonds, and staggered and hexagonal maps shift every other row or column
+//! by half a tile. The grid turns Tiled's tile and object coordinates into positions on the map,
+//! so that spawning doesn't depend on the map's orientation.
+
+use bevy::{
+    math::{ivec2, vec2, IRect, IVec2, Rect, Vec2},
+    sprite::Anchor,
+};
+
+use super::attributes::{MapAttributes, ObjectAlignment, RenderOrder};
+
+/// MapGrid lays out the cells of a map, in Tiled's pixels. Positions on the map are measured from
+/// its top left corner, with y pointing down as in Tiled.
+#[derive(Debug, Clone, Copy)]
+pub struct MapGrid {
+    pub orientation: tiled::Orientation,
+    /// The size of a cell of the grid
+    pub tile_size: Vec2,
+    /// The tiles the map covers, in Tiled's tile coordinates
+    pub bounds: IRect,
+    /// Whether staggered and hexagonal maps shift their columns rather than their rows
+    pub stagger_axis: tiled::StaggerAxis,
+    /// Whether staggered and hexagonal maps shift their even rows or columns rather than their
+    /// odd ones
+    pub stagger_index: tiled::StaggerIndex,
+    /// The length of the flat sides of a hexagonal map's tiles, along the stagger axis
+    pub hex_side_length: f32,
+    /// The order the tiles of orthogonal maps are drawn in
+    pub render_order: RenderOrder,
+    /// How far right Tiled draws the top corner of the tile at 0, 0 of an isometric map
+    origin_x: f32,
+    /// The area the map's cells cover, worked out once as every position on the map is measured
+    /// from it
+    pixel_rect: Rect,
+}
+
+impl MapGrid {
+    pub fn new(map: &tiled::Map, attributes: &MapAttributes, bounds: IRect) -> Self {
+        let tile_size = vec2(map.tile_width as f32, map.tile_height as f32);
+
+        let hex_side_length = match map.orientation {
+            tiled::Orientation::Hexagonal => attributes.hex_side_length,
+            _ => 0.0,
+        };
+
+        Self {
+            orientation: map.orientation,
+            tile_size,
+            bounds,
+            stagger_axis: map.stagger_axis,
+            stagger_index: map.stagger_index,
+            hex_side_length,
+            render_order: attributes.render_order,
+            origin_x: map.height as f32 * tile_size.x / 2.0,
+            pixel_rect: Rect::default(),
+        }
+        .with_pixel_rect()
+    }
+
+    /// The grid with the area its cells cover
+    fn with_pixel_rect(mut self) -> Self {
+        self.pixel_rect = self.cells_rect();
+        self
+    }
+
+    /// The map's tiles fit the rows and columns of a tilemap
+    pub fn is_orthogonal(&self) -> bool {
+        self.orientation == tiled::Orientation::Orthogonal
+    }
+
+    /// The area the map's cells cover, where Tiled draws them
+    pub fn pixel_rect(&self) -> Rect {
+        self.pixel_rect
+    }
+
+    fn cells_rect(&self) -> Rect {
+        let (min, max) = (self.bounds.min, self.bounds.max - IVec2::ONE);
+
+        // The outermost cells of staggered and hexagonal maps can be in the second row or column
+        // from an edge, as every other one is shifted.
+        let near_edges = |min: i32, max: i32| [min, (min + 1).min(max), (max - 1).max(min), max];
+
+        near_edges(min.x, max.x)
+            .into_iter()
+            .flat_map(|x| near_edges(min.y, max.y).map(|y| ivec2(x, y)))
+            .map(|tile| self.cell_rect(tile))
+            .reduce(|rect, cell| rect.union(cell))
+            .unwrap_or_default()
+    }
+
+    /// The size of the area the map's cells cover
+    pub fn pixel_size(&self) -> Vec2 {
+        self.pixel_rect().size()
+    }
+
+    /// The position on the map of the top left corner of the box around a tile's cell
+    pub fn cell_position(&self, tile: IVec2) -> Vec2 {
+        self.cell_rect(tile).min - self.pixel_rect().min
+    }
+
+    /// The position on the map of the center of a tile's cell
+    pub fn cell_center(&self, tile: IVec2) -> Vec2 {
+        self.cell_position(tile) + self.tile_size / 2.0
+    }
+
+    /// The position on the map of a point where Tiled draws it, such as the offset of a layer
+    pub fn pixel_position(&self, point: Vec2) -> Vec2 {
+        point - self.pixel_rect().min
+    }
+
+    /// The position on the map of an object's coordinates. The objects of isometric maps are
+    /// placed in the space of the grid, measured in tile heights along both of its axes. Those of
+    /// other maps are placed where Tiled draws them.
+    pub fn object_position(&self, point: Vec2) -> Vec2 {
+        let point = match self.orientation {
+            tiled::Orientation::Isometric => {
+                let tile = point / self.tile_size.y;
+
+                vec2(
+                    self.origin_x + (tile.x - tile.y) * self.tile_size.x / 2.0,
+                    (tile.x + tile.y) * self.tile_size.y / 2.0,
+                )
+            }
+            _ => point,
+        };
+
+        self.pixel_position(point)
+    }
+
+    /// How far through its layer a tile is drawn, from 0 for the first tile to under 1 for the
+    /// last. Orthogonal maps draw their tiles in their render order, and other maps draw the
+    /// tiles lower down the map over those above them.
+    pub fn tile_depth(&self, tile: IVec2) -> f32 {
+        if !self.is_orthogonal() {
+            return self.depth_at(self.cell_position(tile).y);
+        }
+
+        let size = self.bounds.size();
+        if size.x <= 0 || size.y <= 0 {
+            return 0.0;
+        }
+
+        let tile = tile - self.bounds.min;
+        let (column, row) = match self.render_order {
+            RenderOrder::RightDown => (tile.x, tile.y),
+            RenderOrder::RightUp => (tile.x, size.y - 1 - tile.y),
+            RenderOrder::LeftDown => (size.x - 1 - tile.x, tile.y),
+            RenderOrder::LeftUp => (size.x - 1 - tile.x, size.y - 1 - tile.y),
+        };
+
+        (row * size.x + column) as f32 / (size.x * size.y) as f32
+    }
+
+    /// How far through its layer an object at the given coordinates is drawn when objects lower
+    /// down the map are drawn over those above them
+    pub fn object_depth(&self, point: Vec2) -> f32 {
+        self.depth_at(self.object_position(point).y)
+    }
+
+    /// How far down the map a position is, from 0 at the top to under 1 at the bottom
+    fn depth_at(&self, y: f32) -> f32 {
+        let height = self.pixel_size().y;
+        if height <= 0.0 {
+            return 0.0;
+        }
+
+        (y / height).clamp(0.0, 1.0 - f32::EPSILON)
+    }
+
+    /// The point of a tile object's image that is at the object's position, from the object
+    /// alignment of its tileset
+    pub fn tile_object_anchor(&self, alignment: ObjectAlignment) -> Anchor {
+        match alignment {
+            ObjectAlignment::Unspecified => match self.orientation {
+                tiled::Orientation::Isometric => Anchor::BottomCenter,
+                _ => Anchor::BottomLeft,
+            },
+            ObjectAlignment::TopLeft => Anchor::TopLeft,
+            ObjectAlignment::Top => Anchor::TopCenter,
+            ObjectAlignment::TopRight => Anchor::TopRight,
+            ObjectAlignment::Left => Anchor::CenterLeft,
+            ObjectAlignment::Center => Anchor::Center,
+            ObjectAlignment::Right => Anchor::CenterRight,
+            ObjectAlignment::BottomLeft => Anchor::BottomLeft,
+            ObjectAlignment::Bottom => Anchor::BottomCenter,
+            ObjectAlignment::BottomRight => Anchor::BottomRight,
+        }
+    }
+
+    /// The box around a tile's cell, where Tiled draws it
+    fn cell_rect(&self, tile: IVec2) -> Rect {
+        let min = match self.orientation {
+            tiled::Orientation::Orthogonal => tile.as_vec2() * self.tile_size,
+            tiled::Orientation::Isometric => vec2(
+                self.origin_x + (tile.x - tile.y - 1) as f32 * self.tile_size.x / 2.0,
+                (tile.x + tile.y) as f32 * self.tile_size.y / 2.0,
+            ),
+            tiled::Orientation::Staggered | tiled::Orientation::Hexagonal => {
+                self.staggered_cell_position(tile)
+            }
+        };
+
+        Rect::from_corners(min, min + self.tile_size)
+    }
+
+    /// Where Tiled draws a tile of a staggered or hexagonal map. Staggered maps are hexagonal
+    /// maps whose tiles have no flat sides.
+    fn staggered_cell_position(&self, tile: IVec2) -> Vec2 {
+        // Tiled lays out hexagons on even pixel sizes, and whole pixels either side of the flat
+        // sides.
+        let tile_size = (self.tile_size / 2.0).floor() * 2.0;
+        let stagger_even = self.stagger_index == tiled::StaggerIndex::Even;
+        let is_staggered = |index: i32| (index & 1 == 1) != stagger_even;
+
+        match self.stagger_axis {
+            tiled::StaggerAxis::X => {
+                let column_width =
+                    ((tile_size.x - self.hex_side_length) / 2.0).floor() + self.hex_side_length;
+                let row_height = tile_size.y / 2.0;
+
+                let x = tile.x as f32 * column_width;
+                let mut y = tile.y as f32 * tile_size.y;
+                if is_staggered(tile.x) {
+                    y += row_height;
+                }
+
+                vec2(x, y)
+            }
+            tiled::StaggerAxis::Y => {
+                let column_width = tile_size.x / 2.0;
+                let row_height =
+                    ((tile_size.y - self.hex_side_length) / 2.0).floor() + self.hex_side_length;
+
+                let mut x = tile.x as f32 * tile_size.x;
+                let y = tile.y as f32 * row_height;
+                if is_staggered(tile.y) {
+                    x += column_width;
+                }
+
+                vec2(x, y)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(
+        orientation: tiled::Orientation,
+        tile_size: Vec2,
+        hex_side_length: f32,
+        stagger_axis: tiled::StaggerAxis,
+        stagger_index: tiled::StaggerIndex,
+    ) -> MapGrid {
+        MapGrid {
+            orientation,
+            tile_size,
+            bounds: IRect::new(0, 0, 3, 2),
+            stagger_axis,
+            stagger_index,
+            hex_side_length,
+            render_order: RenderOrder::RightDown,
+            origin_x: 0.0,
+            pixel_rect: Rect::default(),
+        }
+        .with_pixel_rect()
+    }
+
+    fn hexagonal(
+        hex_side_length: f32,
+        stagger_axis: tiled::StaggerAxis,
+        stagger_index: tiled::StaggerIndex,
+    ) -> MapGrid {
+        grid(
+            tiled::Orientation::Hexagonal,
+            vec2(32.0, 32.0),
+            hex_side_length,
+            stagger_axis,
+            stagger_index,
+        )
+    }
+
+    fn staggered(stagger_axis: tiled::StaggerAxis, stagger_index: tiled::StaggerIndex) -> MapGrid {
+        grid(
+            tiled::Orientation::Staggered,
+            vec2(32.0, 16.0),
+            0.0,
+            stagger_axis,
+            stagger_index,
+        )
+    }
+
+    /// A tile and where its cell is drawn
+    type Cell = ((i32, i32), (f32, f32));
+
+    fn assert_cells(grid: &MapGrid, cells: &[Cell]) {
+        for &((x, y), (pixel_x, pixel_y)) in cells {
+            assert_eq!(
+                grid.staggered_cell_position(ivec2(x, y)),
+                vec2(pixel_x, pixel_y),
+                "tile {x}, {y} of {grid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hexagonal_cells_staggered_along_x() {
+        use tiled::{StaggerAxis::X, StaggerIndex};
+
+        assert_cells(
+            &hexagonal(16.0, X, StaggerIndex::Odd),
+            &[
+                ((0, 0), (0.0, 0.0)),
+                ((1, 0), (24.0, 16.0)),
+                ((2, 0), (48.0, 0.0)),
+                ((1, 1), (24.0, 48.0)),
+            ],
+        );
+        assert_cells(
+            &hexagonal(16.0, X, StaggerIndex::Even),
+            &[
+                ((0, 0), (0.0, 16.0)),
+                ((1, 0), (24.0, 0.0)),
+                ((2, 1), (48.0, 48.0)),
+            ],
+        );
+    }
+
+    #[test]
+    fn hexagonal_cells_staggered_along_y() {
+        use tiled::{StaggerAxis::Y, StaggerIndex};
+
+        assert_cells(
+            &hexagonal(16.0, Y, StaggerIndex::Odd),
+            &[
+                ((1, 0), (32.0, 0.0)),
+                ((0, 1), (16.0, 24.0)),
+                ((1, 1), (48.0, 24.0)),
+                ((0, 2), (0.0, 48.0)),
+            ],
+        );
+        assert_cells(
+            &hexagonal(16.0, Y, StaggerIndex::Even),
+            &[
+                ((0, 0), (16.0, 0.0)),
+                ((0, 1), (0.0, 24.0)),
+                ((1, 2), (48.0, 48.0)),
+            ],
+        );
+    }
+
+    #[test]
+    fn hexagonal_cells_with_odd_side_lengths_use_whole_pixels() {
+        use tiled::{StaggerAxis, StaggerIndex::Odd};
+
+        // Like Tiled, (32 - 15) / 2 rounds down to 8 either side of the flat side.
+        assert_cells(
+            &hexagonal(15.0, StaggerAxis::X, Odd),
+            &[((1, 0), (23.0, 16.0)), ((2, 1), (46.0, 32.0))],
+        );
+        assert_cells(
+            &hexagonal(15.0, StaggerAxis::Y, Odd),
+            &[((0, 1), (16.0, 23.0)), ((1, 2), (32.0, 46.0))],
+        );
+    }
+
+    #[test]
+    fn staggered_cells_without_flat_sides() {
+        use tiled::{StaggerAxis, StaggerIndex};
+
+        assert_cells(
+            &staggered(StaggerAxis::X, StaggerIndex::Odd),
+            &[
+                ((1, 0), (16.0, 8.0)),
+                ((1, 1), (16.0, 24.0)),
+                ((2, 1), (32.0, 16.0)),
+            ],
+        );
+        assert_cells(
+            &staggered(StaggerAxis::X, StaggerIndex::Even),
+            &[((0, 0), (0.0, 8.0)), ((1, 1), (16.0, 16.0))],
+        );
+        assert_cells(
+            &staggered(StaggerAxis::Y, StaggerIndex::Odd),
+            &[((0, 1), (16.0, 8.0)), ((1, 2), (32.0, 16.0))],
+        );
+        assert_cells(
+            &staggered(StaggerAxis::Y, StaggerIndex::Even),
+            &[((0, 0), (16.0, 0.0)), ((1, 1), (32.0, 8.0))],
+        );
+    }
+
+    #[test]
+    fn staggered_cells_use_even_tile_sizes() {
+        let grid = grid(
+            tiled::Orientation::Staggered,
+            vec2(33.0, 17.0),
+            0.0,
+            tiled::StaggerAxis::Y,
+            tiled::StaggerIndex::Odd,
+        );
+
+        assert_cells(&grid, &[((1, 1), (48.0, 8.0))]);
+    }
+
+    #[test]
+    fn pixel_rect_covers_staggered_cells() {
+        use tiled::{StaggerAxis, StaggerIndex};
+
+        // Three columns of two tiles, the middle one shifted down or the outer ones
+        for stagger_index in [StaggerIndex::Odd, StaggerIndex::Even] {
+            let grid = hexagonal(16.0, StaggerAxis::X, stagger_index);
+            assert_eq!(grid.pixel_rect(), Rect::new(0.0, 0.0, 80.0, 80.0));
+        }
+
+        // Two rows of three tiles, the second shifted right or the first
+        for stagger_index in [StaggerIndex::Odd, StaggerIndex::Even] {
+            let grid = hexagonal(16.0, StaggerAxis::Y, stagger_index);
+            assert_eq!(grid.pixel_rect(), Rect::new(0.0, 0.0, 112.0, 56.0));
+        }
+
+        let grid = staggered(StaggerAxis::Y, StaggerIndex::Odd);
+        assert_eq!(grid.pixel_rect(), Rect::new(0.0, 0.0, 112.0, 24.0));
+    }
+
+    #[test]
+    fn cell_positions_are_measured_from_the_map_corner() {
+        let grid = hexagonal(16.0, tiled::StaggerAxis::X, tiled::StaggerIndex::Even);
+
+        // The first column is shifted down, but the second isn't, so the map starts at 0, 0.
+        assert_eq!(grid.cell_position(ivec2(0, 0)), vec2(0.0, 16.0));
+        assert_eq!(grid.cell_center(ivec2(1, 0)), vec2(40.0, 16.0));
+    }
+}
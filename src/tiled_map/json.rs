@@ -0,0 +1,750 @@
+//! Conversion of the Tiled JSON formats (`.tmj` maps, `.tsj` tilesets and `.tj` templates) into
+//! their XML counterparts, so that [tiled::Loader] can parse them and the loader produces the same
+//! [TiledMap](super::TiledMap) whichever format was exported from Tiled.
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// File extensions that hold Tiled JSON documents.
+const JSON_EXTENSIONS: &[&str] = &["tmj", "tsj", "tj", "json"];
+
+#[derive(Debug, Error)]
+pub enum TiledJsonError {
+    /// The file is not valid JSON
+    #[error("Could not parse Tiled JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The JSON does not describe a map, tileset or template
+    #[error("Unsupported Tiled JSON document: {0}")]
+    Unsupported(String),
+}
+
+/// Returns true if the file at `path` is a Tiled JSON document.
+pub fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| JSON_EXTENSIONS.contains(&extension))
+}
+
+/// Convert a Tiled JSON map, tileset or template into the equivalent TMX, TSX or TX document.
+pub fn to_xml(path: &Path, bytes: &[u8]) -> Result<Vec<u8>, TiledJsonError> {
+    let value: Value = serde_json::from_slice(bytes)?;
+    let Value::Object(document) = value else {
+        return Err(TiledJsonError::Unsupported(path.display().to_string()));
+    };
+
+    // Older exports don't include the document type, so fall back on the file extension.
+    let document_type = match document.get("type").and_then(Value::as_str) {
+        Some(document_type) => document_type,
+        None => match path.extension().and_then(|extension| extension.to_str()) {
+            Some("tsj") => "tileset",
+            Some("tj") => "template",
+            _ => "map",
+        },
+    };
+
+    let root = match document_type {
+        "map" => map_element(&document),
+        "tileset" => tileset_element(&document),
+        "template" => template_element(&document),
+        other => return Err(TiledJsonError::Unsupported(other.to_string())),
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    root.write(&mut xml);
+
+    Ok(xml.into_bytes())
+}
+
+/// A minimal XML element tree used to build the converted document.
+struct Element {
+    tag: &'static str,
+    attributes: Vec<(&'static str, String)>,
+    children: Vec<Element>,
+    text: Option<String>,
+}
+
+impl Element {
+    fn new(tag: &'static str) -> Self {
+        Self {
+            tag,
+            attributes: vec![],
+            children: vec![],
+            text: None,
+        }
+    }
+
+    fn attribute(&mut self, name: &'static str, value: impl Into<String>) {
+        self.attributes.push((name, value.into()));
+    }
+
+    /// Copy the named JSON fields across as attributes of the same name, if present.
+    fn attributes_from(&mut self, json: &Map<String, Value>, names: &[&'static str]) {
+        for name in names {
+            if let Some(value) = json.get(*name).and_then(attribute_value) {
+                self.attribute(name, value);
+            }
+        }
+    }
+
+    fn write(&self, xml: &mut String) {
+        xml.push('<');
+        xml.push_str(self.tag);
+
+        for (name, value) in &self.attributes {
+            xml.push(' ');
+            xml.push_str(name);
+            xml.push_str("=\"");
+            xml.push_str(&escape(value));
+            xml.push('"');
+        }
+
+        if self.children.is_empty() && self.text.is_none() {
+            xml.push_str("/>\n");
+            return;
+        }
+
+        xml.push('>');
+
+        if let Some(text) = &self.text {
+            xml.push_str(&escape(text));
+        } else {
+            xml.push('\n');
+        }
+
+        for child in &self.children {
+            child.write(xml);
+        }
+
+        xml.push_str("</");
+        xml.push_str(self.tag);
+        xml.push_str(">\n");
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Format a JSON value the way TMX writes attributes, booleans being `1` or `0`.
+fn attribute_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Bool(b) => Some(if *b { "1" } else { "0" }.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn objects<'a>(
+    json: &'a Map<String, Value>,
+    key: &str,
+) -> impl Iterator<Item = &'a Map<String, Value>> {
+    json.get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+}
+
+fn map_element(json: &Map<String, Value>) -> Element {
+    let mut map = Element::new("map");
+    map.attributes_from(
+        json,
+        &[
+            "version",
+            "tiledversion",
+            "class",
+            "orientation",
+            "renderorder",
+            "compressionlevel",
+            "width",
+            "height",
+            "tilewidth",
+            "tileheight",
+            "hexsidelength",
+            "staggeraxis",
+            "staggerindex",
+            "parallaxoriginx",
+            "parallaxoriginy",
+            "backgroundcolor",
+            "infinite",
+            "nextlayerid",
+            "nextobjectid",
+        ],
+    );
+
+    map.children.extend(properties_element(json));
+
+    for tileset in objects(json, "tilesets") {
+        map.children.push(tileset_element(tileset));
+    }
+
+    for layer in objects(json, "layers") {
+        map.children.extend(layer_element(layer));
+    }
+
+    map
+}
+
+fn tileset_element(json: &Map<String, Value>) -> Element {
+    let mut tileset = Element::new("tileset");
+    tileset.attributes_from(
+        json,
+        &[
+            "firstgid",
+            "source",
+            "version",
+            "tiledversion",
+            "name",
+            "class",
+            "tilewidth",
+            "tileheight",
+            "spacing",
+            "margin",
+            "tilecount",
+            "columns",
+            "objectalignment",
+        ],
+    );
+
+    if let Some(offset) = json.get("tileoffset").and_then(Value::as_object) {
+        let mut tileoffset = Element::new("tileoffset");
+        tileoffset.attributes_from(offset, &["x", "y"]);
+        tileset.children.push(tileoffset);
+    }
+
+    tileset.children.extend(properties_element(json));
+    tileset.children.extend(image_element(json));
+
+    for tile in objects(json, "tiles") {
+        tileset.children.push(tile_element(tile));
+    }
+
+    if let Some(wangsets) = json.get("wangsets").and_then(Value::as_array) {
+        let mut element = Element::new("wangsets");
+        for wangset in wangsets.iter().filter_map(Value::as_object) {
+            element.children.push(wangset_element(wangset));
+        }
+        tileset.children.push(element);
+    }
+
+    tileset
+}
+
+fn tile_element(json: &Map<String, Value>) -> Element {
+    let mut tile = Element::new("tile");
+    tile.attributes_from(json, &["id", "type", "class", "probability"]);
+
+    tile.children.extend(properties_element(json));
+    tile.children.extend(image_element(json));
+
+    if let Some(objectgroup) = json.get("objectgroup").and_then(Value::as_object) {
+        tile.children.extend(layer_element(objectgroup));
+    }
+
+    if let Some(frames) = json.get("animation").and_then(Value::as_array) {
+        let mut animation = Element::new("animation");
+        for frame in frames.iter().filter_map(Value::as_object) {
+            let mut element = Element::new("frame");
+            element.attributes_from(frame, &["tileid", "duration"]);
+            animation.children.push(element);
+        }
+        tile.children.push(animation);
+    }
+
+    tile
+}
+
+fn wangset_element(json: &Map<String, Value>) -> Element {
+    let mut wangset = Element::new("wangset");
+    wangset.attributes_from(json, &["name", "class", "type", "tile"]);
+    wangset.children.extend(properties_element(json));
+
+    for color in objects(json, "colors") {
+        let mut element = Element::new("wangcolor");
+        element.attributes_from(color, &["name", "class", "color", "tile", "probability"]);
+        element.children.extend(properties_element(color));
+        wangset.children.push(element);
+    }
+
+    for tile in objects(json, "wangtiles") {
+        let mut element = Element::new("wangtile");
+        element.attributes_from(tile, &["tileid"]);
+        if let Some(wangid) = tile.get("wangid").and_then(Value::as_array) {
+            element.attribute("wangid", join(wangid));
+        }
+        wangset.children.push(element);
+    }
+
+    wangset
+}
+
+fn template_element(json: &Map<String, Value>) -> Element {
+    let mut template = Element::new("template");
+
+    if let Some(tileset) = json.get("tileset").and_then(Value::as_object) {
+        template.children.push(tileset_element(tileset));
+    }
+
+    if let Some(object) = json.get("object").and_then(Value::as_object) {
+        template.children.push(object_element(object));
+    }
+
+    template
+}
+
+fn layer_element(json: &Map<String, Value>) -> Option<Element> {
+    // Tile objectgroups don't carry a layer type, everything else does.
+    let layer_type = json
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("objectgroup");
+
+    let tag = match layer_type {
+        "tilelayer" => "layer",
+        "objectgroup" => "objectgroup",
+        "imagelayer" => "imagelayer",
+        "group" => "group",
+        _ => return None,
+    };
+
+    let mut layer = Element::new(tag);
+    layer.attributes_from(
+        json,
+        &[
+            "id",
+            "name",
+            "class",
+            "width",
+            "height",
+            "opacity",
+            "visible",
+            "locked",
+            "tintcolor",
+            "offsetx",
+            "offsety",
+            "parallaxx",
+            "parallaxy",
+            "color",
+            "draworder",
+            "repeatx",
+            "repeaty",
+        ],
+    );
+
+    layer.children.extend(properties_element(json));
+
+    match tag {
+        "layer" => layer.children.push(data_element(json)),
+        "objectgroup" => {
+            for object in objects(json, "objects") {
+                layer.children.push(object_element(object));
+            }
+        }
+        "imagelayer" => layer.children.extend(image_element(json)),
+        _ => {
+            for child in objects(json, "layers") {
+                layer.children.extend(layer_element(child));
+            }
+        }
+    }
+
+    Some(layer)
+}
+
+fn data_element(json: &Map<String, Value>) -> Element {
+    let mut data = Element::new("data");
+
+    let encoding = json
+        .get("encoding")
+        .and_then(Value::as_str)
+        .unwrap_or("csv");
+    data.attribute("encoding", encoding);
+
+    if let Some(compression) = json
+        .get("compression")
+        .and_then(Value::as_str)
+        .filter(|compression| !compression.is_empty())
+    {
+        data.attribute("compression", compression);
+    }
+
+    if json.contains_key("chunks") {
+        for chunk in objects(json, "chunks") {
+            let mut element = Element::new("chunk");
+            element.attributes_from(chunk, &["x", "y", "width", "height"]);
+            element.text = chunk.get("data").map(tile_data);
+            data.children.push(element);
+        }
+    } else {
+        data.text = json.get("data").map(tile_data);
+    }
+
+    data
+}
+
+/// Tile data is either an array of GIDs, written out as CSV, or an already encoded base64 string.
+fn tile_data(value: &Value) -> String {
+    match value {
+        Value::Array(gids) => join(gids),
+        Value::String(encoded) => encoded.clone(),
+        _ => String::new(),
+    }
+}
+
+fn join(values: &[Value]) -> String {
+    values
+        .iter()
+        .filter_map(attribute_value)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn object_element(json: &Map<String, Value>) -> Element {
+    let mut object = Element::new("object");
+    object.attributes_from(
+        json,
+        &[
+            "id", "template", "name", "type", "class", "gid", "x", "y", "width", "height",
+            "rotation", "visible",
+        ],
+    );
+
+    object.children.extend(properties_element(json));
+
+    if json.get("ellipse").and_then(Value::as_bool) == Some(true) {
+        object.children.push(Element::new("ellipse"));
+    }
+
+    if json.get("point").and_then(Value::as_bool) == Some(true) {
+        object.children.push(Element::new("point"));
+    }
+
+    for tag in ["polygon", "polyline"] {
+        if let Some(points) = json.get(tag).and_then(Value::as_array) {
+            let points = points
+                .iter()
+                .filter_map(Value::as_object)
+                .filter_map(|point| {
+                    let x = point.get("x").and_then(attribute_value)?;
+                    let y = point.get("y").and_then(attribute_value)?;
+                    Some(format!("{x},{y}"))
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            let mut element = Element::new(tag);
+            element.attribute("points", points);
+            object.children.push(element);
+        }
+    }
+
+    if let Some(text) = json.get("text").and_then(Value::as_object) {
+        let mut element = Element::new("text");
+        element.attributes_from(
+            text,
+            &[
+                "fontfamily",
+                "pixelsize",
+                "wrap",
+                "color",
+                "bold",
+                "italic",
+                "underline",
+                "strikeout",
+                "kerning",
+                "halign",
+                "valign",
+            ],
+        );
+        element.text = text.get("text").and_then(Value::as_str).map(str::to_string);
+        object.children.push(element);
+    }
+
+    object
+}
+
+fn image_element(json: &Map<String, Value>) -> Option<Element> {
+    let source = json.get("image").and_then(Value::as_str)?;
+
+    let mut image = Element::new("image");
+    image.attribute("source", source);
+
+    if let Some(trans) = json.get("transparentcolor").and_then(Value::as_str) {
+        image.attribute("trans", trans.trim_start_matches('#'));
+    }
+
+    if let Some(width) = json.get("imagewidth").and_then(attribute_value) {
+        image.attribute("width", width);
+    }
+
+    if let Some(height) = json.get("imageheight").and_then(attribute_value) {
+        image.attribute("height", height);
+    }
+
+    Some(image)
+}
+
+fn properties_element(json: &Map<String, Value>) -> Option<Element> {
+    let properties = json.get("properties")?.as_array()?;
+
+    let mut element = Element::new("properties");
+    for property in properties.iter().filter_map(Value::as_object) {
+        element.children.push(property_element(property));
+    }
+
+    Some(element)
+}
+
+fn property_element(json: &Map<String, Value>) -> Element {
+    let mut property = Element::new("property");
+    property.attributes_from(json, &["name", "type", "propertytype"]);
+
+    match json.get("value") {
+        // Class values are nested properties in TMX.
+        Some(Value::Object(members)) => {
+            let mut properties = Element::new("properties");
+            for (name, value) in members {
+                properties.children.push(member_element(name, value));
+            }
+            property.children.push(properties);
+        }
+        // Unlike other attributes, boolean property values are written as true/false.
+        Some(Value::Bool(b)) => property.attribute("value", b.to_string()),
+        Some(value) => {
+            if let Some(value) = attribute_value(value) {
+                property.attribute("value", value);
+            }
+        }
+        None => (),
+    }
+
+    property
+}
+
+/// JSON class members only carry a value, so their type is inferred from it.
+fn member_element(name: &str, value: &Value) -> Element {
+    let mut json = Map::new();
+    json.insert("name".to_string(), Value::String(name.to_string()));
+    json.insert(
+        "type".to_string(),
+        Value::String(member_type(value).to_string()),
+    );
+    json.insert("value".to_string(), value.clone());
+
+    property_element(&json)
+}
+
+/// The property type of a class member written in JSON, which only gives its value. Colors and
+/// files can't be told apart from strings.
+pub fn member_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::Object(_) => "class",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::tiled_map::{properties, BytesResourceReader, TiledProperties, TiledPropertyValue};
+
+    /// Convert the given JSON files the way the loader does, and parse the map with tiled.
+    fn load_map(map_path: &str, files: &[(&str, Value)]) -> tiled::Map {
+        let files: HashMap<PathBuf, _> = files
+            .iter()
+            .map(|(path, value)| {
+                let path = PathBuf::from(path);
+                let xml = to_xml(&path, value.to_string().as_bytes()).unwrap();
+                let xml = properties::flatten_class_properties(&xml).unwrap();
+                (path, xml.into())
+            })
+            .collect();
+
+        tiled::Loader::with_cache_and_reader(
+            tiled::DefaultResourceCache::new(),
+            BytesResourceReader { files },
+        )
+        .load_tmx_map(map_path)
+        .unwrap()
+    }
+
+    #[test]
+    fn converts_map_with_external_tileset_chunks_and_objects() {
+        // A chunk of the infinite layer, left of the origin, with one tile in it
+        let mut chunk = vec![0; 16 * 16];
+        chunk[2 * 16 + 3] = 2;
+
+        let tileset = json!({
+            "type": "tileset",
+            "name": "tiles",
+            "tilewidth": 16,
+            "tileheight": 16,
+            "tilecount": 4,
+            "columns": 2,
+            "margin": 0,
+            "spacing": 0,
+            "image": "tiles.png",
+            "imagewidth": 32,
+            "imageheight": 32,
+        });
+
+        let map = json!({
+            "type": "map",
+            "version": "1.10",
+            "tiledversion": "1.10.2",
+            "orientation": "orthogonal",
+            "renderorder": "right-down",
+            "width": 16,
+            "height": 16,
+            "tilewidth": 16,
+            "tileheight": 16,
+            "infinite": true,
+            "tilesets": [{ "firstgid": 1, "source": "tilesets/tiles.tsj" }],
+            "layers": [
+                {
+                    "type": "tilelayer",
+                    "id": 1,
+                    "name": "Ground",
+                    "width": 16,
+                    "height": 16,
+                    "startx": -16,
+                    "starty": 0,
+                    "chunks": [{ "x": -16, "y": 0, "width": 16, "height": 16, "data": chunk }],
+                    "opacity": 1,
+                    "visible": true,
+                    "x": 0,
+                    "y": 0,
+                },
+                {
+                    "type": "objectgroup",
+                    "id": 2,
+                    "name": "Objects",
+                    "objects": [{
+                        "id": 1,
+                        "name": "Area",
+                        "type": "Zone",
+                        "x": 32,
+                        "y": 48,
+                        "width": 0,
+                        "height": 0,
+                        "rotation": 0,
+                        "visible": true,
+                        "polygon": [{ "x": 0, "y": 0 }, { "x": 16, "y": 0 }, { "x": 8, "y": -8.5 }],
+                        "properties": [{
+                            "name": "loot",
+                            "type": "class",
+                            "propertytype": "Loot",
+                            "value": { "count": 3, "rare": true, "weight": 1.5 },
+                        }],
+                    }],
+                    "opacity": 1,
+                    "visible": true,
+                    "x": 0,
+                    "y": 0,
+                },
+            ],
+        });
+
+        let map = load_map(
+            "maps/level.tmj",
+            &[
+                ("maps/level.tmj", map),
+                ("maps/tilesets/tiles.tsj", tileset),
+            ],
+        );
+
+        assert!(map.infinite());
+        assert_eq!(map.tilesets().len(), 1);
+        assert_eq!(map.tilesets()[0].name, "tiles");
+        assert_eq!(map.tilesets()[0].tilecount, 4);
+
+        let layers: Vec<_> = map.layers().collect();
+        assert_eq!(layers.len(), 2);
+
+        let tiled::LayerType::Tiles(tiled::TileLayer::Infinite(ground)) = layers[0].layer_type()
+        else {
+            panic!("the first layer should be an infinite tile layer");
+        };
+        let tile = ground.get_tile(-16 + 3, 2).expect("the chunk's tile");
+        assert_eq!(tile.id(), 1);
+        assert!(ground.get_tile(-16 + 4, 2).is_none());
+
+        let tiled::LayerType::Objects(objects) = layers[1].layer_type() else {
+            panic!("the second layer should be an object layer");
+        };
+        let object = objects.get_object(0).unwrap();
+        assert_eq!(object.name, "Area");
+        assert_eq!(object.user_type, "Zone");
+        assert_eq!((object.x, object.y), (32.0, 48.0));
+
+        let tiled::ObjectShape::Polygon { points } = &object.shape else {
+            panic!("the object should be a polygon");
+        };
+        assert_eq!(points, &vec![(0.0, 0.0), (16.0, 0.0), (8.0, -8.5)]);
+
+        let properties = TiledProperties::from(&object.properties);
+        let Some(TiledPropertyValue::Class { class, properties }) = properties.get("loot") else {
+            panic!("loot should be a class property");
+        };
+        assert_eq!(class, "Loot");
+        assert_eq!(properties.get_int("count"), Some(3));
+        assert_eq!(properties.get_bool("rare"), Some(true));
+        assert_eq!(properties.get_float("weight"), Some(1.5));
+    }
+
+    #[test]
+    fn infers_the_document_type_from_the_extension() {
+        let tileset = json!({
+            "name": "tiles",
+            "tilewidth": 8,
+            "tileheight": 8,
+            "tilecount": 1,
+            "columns": 1,
+            "image": "tiles.png",
+            "imagewidth": 8,
+            "imageheight": 8,
+        });
+
+        let xml = to_xml(Path::new("tiles.tsj"), tileset.to_string().as_bytes()).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+
+        assert!(xml.contains("<tileset name=\"tiles\""));
+        assert!(xml.contains("<image source=\"tiles.png\" width=\"8\" height=\"8\"/>"));
+    }
+
+    #[test]
+    fn rejects_unknown_documents() {
+        let document = json!({ "type": "world" });
+
+        assert!(matches!(
+            to_xml(Path::new("level.world"), document.to_string().as_bytes()),
+            Err(TiledJsonError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+//! Parallax scrolling. Layers with a parallax factor follow the camera at a fraction of its
+//! movement, so that they scroll slower or faster than the rest of the map, the way Tiled previews
+//! them.
+
+use bevy::{
+    core_pipeline::core_2d::Camera2d,
+    math::Vec2,
+    prelude::{
+        Camera, Component, GlobalTransform, Parent, Query, ReflectComponent, Transform, With,
+        Without,
+    },
+    reflect::Reflect,
+};
+
+/// TiledParallax moves a layer entity with the camera, following its parallax factor
+#[derive(Reflect, Component, Debug, Clone, Copy)]
+#[reflect(Component)]
+pub struct TiledParallax {
+    /// How much the layer scrolls as the camera moves: 1 scrolls with the map, 0 stays fixed to
+    /// the camera
+    pub factor: Vec2,
+    /// The camera position, in the space of the layer's parent, at which the layer is drawn where
+    /// it is placed in Tiled
+    pub origin: Vec2,
+    /// The translation of the layer when the camera is at the origin
+    pub translation: Vec2,
+}
+
+impl Default for TiledParallax {
+    fn default() -> Self {
+        Self {
+            factor: Vec2::ONE,
+            origin: Vec2::ZERO,
+            translation: Vec2::ZERO,
+        }
+    }
+}
+
+impl TiledParallax {
+    /// The parallax of a layer, if it scrolls any differently from the map
+    pub fn new(factor: Vec2, origin: Vec2, translation: Vec2) -> Option<Self> {
+        if factor == Vec2::ONE {
+            return None;
+        }
+
+        Some(Self {
+            factor,
+            origin,
+            translation,
+        })
+    }
+}
+
+/// Offset every parallax layer by how far the active 2D camera is from the parallax origin.
+pub fn scroll_parallax_layers(
+    camera_query: Query<(&Camera, &Transform), With<Camera2d>>,
+    mut layer_query: Query<(&TiledParallax, &mut Transform, &Parent), Without<Camera2d>>,
+    parent_query: Query<&GlobalTransform>,
+) {
+    let Some((_, camera_transform)) = camera_query.iter().find(|(camera, _)| camera.is_active)
+    else {
+        return;
+    };
+
+    for (parallax, mut transform, parent) in layer_query.iter_mut() {
+        let Ok(parent_transform) = parent_query.get(parent.get()) else {
+            continue;
+        };
+
+        let camera = parent_transform
+            .affine()
+            .inverse()
+            .transform_point3(camera_transform.translation)
+            .truncate();
+
+        let translation =
+            parallax.translation + (camera - parallax.origin) * (Vec2::ONE - parallax.factor);
+
+        if transform.translation.truncate() != translation {
+            transform.translation = translation.extend(transform.translation.z);
+        }
+    }
+}
@@ -0,0 +1,460 @@
+//! Custom types from a Tiled project (`.tiled-project`), or the property types exported from one.
+//! Tiled only writes the class members that differ from their defaults, and stores enums as plain
+//! strings or integers, so the project is needed to give objects their full set of typed
+//! properties.
+
+use std::collections::HashMap;
+
+use bevy::render::color::Color;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{json, TiledProperties, TiledPropertyValue};
+
+/// The custom property types of a Tiled project, by name
+#[derive(Debug, Clone, Default)]
+pub struct TiledProject {
+    pub property_types: HashMap<String, TiledPropertyType>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TiledPropertyType {
+    /// A custom class, with the default value of each of its members
+    Class { members: Vec<TiledClassMember> },
+    /// A custom enum, stored in maps by name or by index
+    Enum {
+        values: Vec<String>,
+        stored_as_int: bool,
+        /// Several values can be set at once, stored as a comma separated list of names or as a
+        /// bit per value
+        flags: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct TiledClassMember {
+    pub name: String,
+    /// The custom type of the member, if it is a class or enum
+    pub property_type: Option<String>,
+    pub default: TiledPropertyValue,
+}
+
+impl TiledProject {
+    /// Fill in the members of `class` missing from `properties` with their defaults, and give
+    /// enum members the names of their values. Class properties nested within are completed in
+    /// the same way.
+    pub fn apply(&self, class: Option<&str>, properties: &mut TiledProperties) {
+        if let Some(TiledPropertyType::Class { members }) =
+            class.and_then(|class| self.property_types.get(class))
+        {
+            for member in members {
+                let value = properties
+                    .0
+                    .entry(member.name.clone())
+                    .or_insert_with(|| member.default.clone());
+
+                coerce(value, &member.default);
+
+                match value {
+                    TiledPropertyValue::Class { class, .. } if class.is_empty() => {
+                        *class = member.property_type.clone().unwrap_or_default();
+                    }
+                    TiledPropertyValue::Int(index) => {
+                        if let Some(name) = member
+                            .property_type
+                            .as_ref()
+                            .and_then(|enum_type| self.enum_name(enum_type, *index))
+                        {
+                            *value = TiledPropertyValue::String(name);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        for value in properties.0.values_mut() {
+            if let TiledPropertyValue::Class { class, properties } = value {
+                self.apply(Some(class.as_str()), properties);
+            }
+        }
+    }
+
+    /// The name of an enum value stored by index, or the comma separated names of the flags set
+    fn enum_name(&self, enum_type: &str, index: i32) -> Option<String> {
+        let Some(TiledPropertyType::Enum {
+            values,
+            stored_as_int: true,
+            flags,
+        }) = self.property_types.get(enum_type)
+        else {
+            return None;
+        };
+
+        if *flags {
+            let names: Vec<_> = values
+                .iter()
+                .enumerate()
+                // Values past the width of the stored integer are never set.
+                .filter(|(bit, _)| {
+                    u32::try_from(*bit)
+                        .ok()
+                        .and_then(|bit| 1u32.checked_shl(bit))
+                        .is_some_and(|mask| index as u32 & mask != 0)
+                })
+                .map(|(_, name)| name.as_str())
+                .collect();
+            return Some(names.join(","));
+        }
+
+        values.get(usize::try_from(index).ok()?).cloned()
+    }
+}
+
+/// Give a value read from a map the type of the member's default, where Tiled JSON lost it.
+fn coerce(value: &mut TiledPropertyValue, default: &TiledPropertyValue) {
+    let coerced = match (&value, default) {
+        (TiledPropertyValue::Int(v), TiledPropertyValue::Float(_)) => {
+            TiledPropertyValue::Float(*v as f32)
+        }
+        (TiledPropertyValue::Float(v), TiledPropertyValue::Int(_)) if v.fract() == 0.0 => {
+            TiledPropertyValue::Int(*v as i32)
+        }
+        (TiledPropertyValue::String(v), TiledPropertyValue::File(_)) => {
+            TiledPropertyValue::File(v.clone())
+        }
+        (TiledPropertyValue::String(v), TiledPropertyValue::Color(_)) => match v.parse() {
+            Ok(color) => TiledPropertyValue::from(&tiled::PropertyValue::ColorValue(color)),
+            Err(_) => return,
+        },
+        _ => return,
+    };
+
+    *value = coerced;
+}
+
+/// A `.tiled-project` file, or the list of property types exported from one
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProjectFile {
+    Project {
+        #[serde(rename = "propertyTypes", default)]
+        property_types: Vec<PropertyTypeJson>,
+    },
+    PropertyTypes(Vec<PropertyTypeJson>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum PropertyTypeJson {
+    Class {
+        name: String,
+        #[serde(default)]
+        members: Vec<MemberJson>,
+    },
+    Enum {
+        name: String,
+        #[serde(rename = "storageType", default)]
+        storage_type: Option<String>,
+        #[serde(default)]
+        values: Vec<String>,
+        #[serde(rename = "valuesAsFlags", default)]
+        values_as_flags: bool,
+    },
+}
+
+#[derive(Deserialize)]
+struct MemberJson {
+    name: String,
+    #[serde(rename = "type")]
+    member_type: String,
+    #[serde(rename = "propertyType")]
+    property_type: Option<String>,
+    #[serde(default)]
+    value: Value,
+}
+
+#[derive(Debug, Error)]
+pub enum TiledProjectError {
+    /// The project is not valid JSON
+    #[error("Could not parse Tiled project: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl TiledProject {
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, TiledProjectError> {
+        let property_types = match serde_json::from_slice(bytes)? {
+            ProjectFile::Project { property_types }
+            | ProjectFile::PropertyTypes(property_types) => property_types,
+        };
+
+        let property_types = property_types
+            .into_iter()
+            .map(|property_type| match property_type {
+                PropertyTypeJson::Class { name, members } => {
+                    let members = members
+                        .into_iter()
+                        .map(|member| TiledClassMember {
+                            default: member_default(&member),
+                            name: member.name,
+                            property_type: member.property_type,
+                        })
+                        .collect();
+
+                    (name, TiledPropertyType::Class { members })
+                }
+                PropertyTypeJson::Enum {
+                    name,
+                    storage_type,
+                    values,
+                    values_as_flags,
+                } => (
+                    name,
+                    TiledPropertyType::Enum {
+                        values,
+                        stored_as_int: storage_type.as_deref() == Some("int"),
+                        flags: values_as_flags,
+                    },
+                ),
+            })
+            .collect();
+
+        Ok(Self { property_types })
+    }
+}
+
+fn member_default(member: &MemberJson) -> TiledPropertyValue {
+    json_value(
+        &member.member_type,
+        member.property_type.as_deref(),
+        &member.value,
+    )
+}
+
+fn json_value(value_type: &str, property_type: Option<&str>, value: &Value) -> TiledPropertyValue {
+    match value_type {
+        "bool" => TiledPropertyValue::Bool(value.as_bool().unwrap_or_default()),
+        "int" => TiledPropertyValue::Int(value.as_i64().unwrap_or_default() as i32),
+        "float" => TiledPropertyValue::Float(value.as_f64().unwrap_or_default() as f32),
+        "object" => TiledPropertyValue::Object(value.as_u64().unwrap_or_default() as u32),
+        "file" => TiledPropertyValue::File(value.as_str().unwrap_or_default().to_string()),
+        // Colors that aren't set are written as an empty string
+        "color" => match value.as_str().map(str::parse::<tiled::Color>) {
+            Some(Ok(color)) => TiledPropertyValue::from(&tiled::PropertyValue::ColorValue(color)),
+            _ => TiledPropertyValue::Color(Color::NONE),
+        },
+        // The members of a class default are only given a value, so their type is inferred from
+        // it. The class's own defaults fill in the rest.
+        "class" => {
+            let properties = value
+                .as_object()
+                .map(|members| {
+                    members
+                        .iter()
+                        .map(|(name, value)| {
+                            (
+                                name.clone(),
+                                json_value(json::member_type(value), None, value),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            TiledPropertyValue::Class {
+                class: property_type.unwrap_or_default().to_string(),
+                properties: TiledProperties(properties),
+            }
+        }
+        _ => TiledPropertyValue::String(match value {
+            Value::String(value) => value.clone(),
+            Value::Null => String::new(),
+            value => value.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"{
+        "propertyTypes": [
+            {
+                "type": "enum",
+                "name": "Potion",
+                "storageType": "string",
+                "values": ["Red", "Green", "Blue"],
+                "valuesAsFlags": false
+            },
+            {
+                "type": "enum",
+                "name": "Weapon",
+                "storageType": "int",
+                "values": ["Sword", "Hammer", "Axe"],
+                "valuesAsFlags": false
+            },
+            {
+                "type": "enum",
+                "name": "Tags",
+                "storageType": "int",
+                "values": ["Fire", "Ice", "Poison"],
+                "valuesAsFlags": true
+            },
+            {
+                "type": "class",
+                "name": "Stats",
+                "members": [
+                    { "name": "health", "type": "int", "value": 10 },
+                    { "name": "speed", "type": "float", "value": 1.5 }
+                ]
+            },
+            {
+                "type": "class",
+                "name": "Player",
+                "members": [
+                    { "name": "potion", "type": "string", "propertyType": "Potion", "value": "Red" },
+                    { "name": "weapon", "type": "int", "propertyType": "Weapon", "value": 1 },
+                    { "name": "tags", "type": "int", "propertyType": "Tags", "value": 0 },
+                    { "name": "stats", "type": "class", "propertyType": "Stats", "value": { "health": 20 } }
+                ]
+            }
+        ]
+    }"#;
+
+    fn project() -> TiledProject {
+        TiledProject::from_slice(PROJECT.as_bytes()).unwrap()
+    }
+
+    fn properties(properties: &[(&str, TiledPropertyValue)]) -> TiledProperties {
+        TiledProperties(
+            properties
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    fn string(value: &str) -> TiledPropertyValue {
+        TiledPropertyValue::String(value.to_string())
+    }
+
+    #[test]
+    fn fills_in_class_defaults() {
+        let mut player = TiledProperties::default();
+        project().apply(Some("Player"), &mut player);
+
+        assert_eq!(
+            player,
+            properties(&[
+                ("potion", string("Red")),
+                ("weapon", string("Hammer")),
+                ("tags", string("")),
+                (
+                    "stats",
+                    TiledPropertyValue::Class {
+                        class: "Stats".to_string(),
+                        properties: properties(&[
+                            ("health", TiledPropertyValue::Int(20)),
+                            ("speed", TiledPropertyValue::Float(1.5)),
+                        ]),
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn keeps_values_set_in_the_map() {
+        let mut player = properties(&[
+            ("potion", string("Blue")),
+            (
+                "stats",
+                TiledPropertyValue::Class {
+                    class: "Stats".to_string(),
+                    // Written as an integer by Tiled JSON
+                    properties: properties(&[("speed", TiledPropertyValue::Int(3))]),
+                },
+            ),
+        ]);
+        project().apply(Some("Player"), &mut player);
+
+        assert_eq!(player.get_string("potion"), Some("Blue"));
+
+        let stats = player.get_class("stats").unwrap();
+        assert_eq!(stats.get_int("health"), Some(10));
+        assert_eq!(stats.get_float("speed"), Some(3.0));
+    }
+
+    #[test]
+    fn names_enums_stored_as_int() {
+        let mut player = properties(&[("weapon", TiledPropertyValue::Int(2))]);
+        project().apply(Some("Player"), &mut player);
+
+        assert_eq!(player.get_string("weapon"), Some("Axe"));
+
+        // Out of range values are left as they are
+        let mut player = properties(&[("weapon", TiledPropertyValue::Int(5))]);
+        project().apply(Some("Player"), &mut player);
+
+        assert_eq!(player.get_int("weapon"), Some(5));
+    }
+
+    #[test]
+    fn names_the_flags_set() {
+        let mut player = properties(&[("tags", TiledPropertyValue::Int(0b101))]);
+        project().apply(Some("Player"), &mut player);
+
+        assert_eq!(player.get_string("tags"), Some("Fire,Poison"));
+    }
+
+    #[test]
+    fn ignores_flags_past_the_width_of_the_stored_integer() {
+        let values: Vec<_> = (0..40).map(|bit| format!("\"Flag{bit}\"")).collect();
+        let project = format!(
+            r#"[{{
+                "type": "enum",
+                "name": "Many",
+                "storageType": "int",
+                "values": [{}],
+                "valuesAsFlags": true
+            }}]"#,
+            values.join(",")
+        );
+        let project = TiledProject::from_slice(project.as_bytes()).unwrap();
+
+        assert_eq!(
+            project.enum_name("Many", 1 | (1 << 31)),
+            Some("Flag0,Flag31".to_string())
+        );
+        assert_eq!(
+            project.enum_name("Many", -1).unwrap().split(',').count(),
+            32
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_classes_alone() {
+        let mut other = properties(&[("weapon", TiledPropertyValue::Int(2))]);
+        project().apply(Some("Other"), &mut other);
+        project().apply(None, &mut other);
+
+        assert_eq!(other, properties(&[("weapon", TiledPropertyValue::Int(2))]));
+    }
+
+    #[test]
+    fn reads_exported_property_types() {
+        let exported = r#"[{ "type": "enum", "name": "Potion", "values": ["Red"] }]"#;
+        let project = TiledProject::from_slice(exported.as_bytes()).unwrap();
+
+        assert!(matches!(
+            project.property_types.get("Potion"),
+            Some(TiledPropertyType::Enum {
+                stored_as_int: false,
+                flags: false,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,532 @@
+//! Custom properties of maps, layers, tiles and objects, exposed to gameplay code through the
+//! [TiledProperties] component.
+//!
+//! tiled only understands the primitive property types and refuses to load files that use class
+//! properties. Before a file reaches tiled, every class property is flattened into primitive ones:
+//! a marker holding the class name and one property per member, named after its path within the
+//! class. [TiledProperties] folds them back into [TiledPropertyValue::Class] values.
+
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::{Component, ReflectComponent},
+    reflect::Reflect,
+    render::color::Color,
+};
+use serde_json::Value;
+use thiserror::Error;
+use xml::{reader::XmlEvent, EventReader, EventWriter};
+
+use super::attributes::attribute;
+
+/// Prefix of the flattened property holding the class name of a class property
+const CLASS_PREFIX: &str = "@class:";
+/// Prefix of the flattened properties holding the members of a class property
+const MEMBER_PREFIX: &str = "@member:";
+
+/// TiledProperties holds the custom properties set in Tiled on the map, layer, tile or object an
+/// entity was spawned from
+#[derive(Reflect, Component, Default, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct TiledProperties(pub HashMap<String, TiledPropertyValue>);
+
+/// A custom property value, with the type it was given in Tiled
+// Reflected as an opaque value, as bevy can't derive Reflect for the recursive class variant.
+#[derive(Reflect, Debug, Clone, PartialEq)]
+#[reflect_value(Debug, PartialEq)]
+pub enum TiledPropertyValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Color(Color),
+    /// A path relative to the file the property was set in
+    File(String),
+    /// The ID of another object in the map, or 0 if unset
+    Object(u32),
+    /// An instance of a custom class. Members left at their default value in Tiled are not
+    /// included.
+    Class {
+        class: String,
+        properties: TiledProperties,
+    },
+}
+
+impl TiledProperties {
+    pub fn get(&self, name: &str) -> Option<&TiledPropertyValue> {
+        self.0.get(name)
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            TiledPropertyValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            TiledPropertyValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            TiledPropertyValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a string or file property
+    pub fn get_string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            TiledPropertyValue::String(value) | TiledPropertyValue::File(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_color(&self, name: &str) -> Option<Color> {
+        match self.get(name)? {
+            TiledPropertyValue::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The ID of the object referenced by an object property, if it is set
+    pub fn get_object(&self, name: &str) -> Option<u32> {
+        match self.get(name)? {
+            TiledPropertyValue::Object(0) => None,
+            TiledPropertyValue::Object(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The members of a class property
+    pub fn get_class(&self, name: &str) -> Option<&TiledProperties> {
+        match self.get(name)? {
+            TiledPropertyValue::Class { properties, .. } => Some(properties),
+            _ => None,
+        }
+    }
+
+    /// The properties as a JSON object, so they can be deserialized into game types
+    pub(crate) fn to_json(&self) -> Value {
+        Value::Object(
+            self.0
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_json()))
+                .collect(),
+        )
+    }
+
+    /// Add properties that are not already set, e.g. the properties of a tile under those of a
+    /// tile object, which override them.
+    pub(crate) fn inherit(&mut self, defaults: &TiledProperties) {
+        for (name, value) in defaults.0.iter() {
+            self.0.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Insert a flattened class member, where `path` is the member name prefixed by the names of
+    /// the class properties it is nested in.
+    fn insert_member(&mut self, path: &str, value: TiledPropertyValue) {
+        // Property names may themselves contain dots, so look for a class at each of them.
+        for (index, _) in path.match_indices('.') {
+            if let Some(TiledPropertyValue::Class { properties, .. }) =
+                self.0.get_mut(&path[..index])
+            {
+                properties.insert_member(&path[index + 1..], value);
+                return;
+            }
+        }
+
+        self.0.insert(path.to_string(), value);
+    }
+}
+
+impl TiledPropertyValue {
+    fn to_json(&self) -> Value {
+        match self {
+            Self::Bool(value) => Value::from(*value),
+            Self::Int(value) => Value::from(*value),
+            Self::Float(value) => Value::from(*value),
+            Self::String(value) | Self::File(value) => Value::from(value.clone()),
+            // Colors are written the way Tiled does, as #AARRGGBB
+            Self::Color(color) => {
+                let [red, green, blue, alpha] = color.as_rgba_u8();
+                Value::from(format!("#{alpha:02x}{red:02x}{green:02x}{blue:02x}"))
+            }
+            Self::Object(value) => Value::from(*value),
+            Self::Class { properties, .. } => properties.to_json(),
+        }
+    }
+}
+
+impl From<&tiled::Properties> for TiledProperties {
+    fn from(properties: &tiled::Properties) -> Self {
+        let mut result = Self::default();
+
+        // Create the classes before their members, and outer classes before nested ones.
+        let mut classes: Vec<_> = properties
+            .iter()
+            .filter_map(|(name, value)| match value {
+                tiled::PropertyValue::StringValue(class) => {
+                    Some((name.strip_prefix(CLASS_PREFIX)?, class))
+                }
+                _ => None,
+            })
+            .collect();
+        classes.sort_by_key(|(path, _)| path.len());
+
+        for (path, class) in classes {
+            result.insert_member(
+                path,
+                TiledPropertyValue::Class {
+                    class: class.clone(),
+                    properties: TiledProperties::default(),
+                },
+            );
+        }
+
+        for (name, value) in properties.iter() {
+            if name.starts_with(CLASS_PREFIX) {
+                continue;
+            }
+
+            let value = TiledPropertyValue::from(value);
+
+            match name.strip_prefix(MEMBER_PREFIX) {
+                Some(path) => result.insert_member(path, value),
+                None => {
+                    result.0.insert(name.clone(), value);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl From<&tiled::PropertyValue> for TiledPropertyValue {
+    fn from(value: &tiled::PropertyValue) -> Self {
+        match value {
+            tiled::PropertyValue::BoolValue(value) => Self::Bool(*value),
+            tiled::PropertyValue::IntValue(value) => Self::Int(*value),
+            tiled::PropertyValue::FloatValue(value) => Self::Float(*value),
+            tiled::PropertyValue::StringValue(value) => Self::String(value.clone()),
+            tiled::PropertyValue::ColorValue(color) => Self::Color(Color::rgba_u8(
+                color.red,
+                color.green,
+                color.blue,
+                color.alpha,
+            )),
+            tiled::PropertyValue::FileValue(value) => Self::File(value.clone()),
+            tiled::PropertyValue::ObjectValue(value) => Self::Object(*value),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ClassPropertiesError {
+    #[error("Could not read Tiled XML: {0}")]
+    Read(#[from] xml::reader::Error),
+    #[error("Could not write Tiled XML: {0}")]
+    Write(#[from] xml::writer::Error),
+}
+
+/// Returns true if the Tiled XML document uses class properties, which need flattening.
+pub fn has_class_properties(bytes: &[u8]) -> bool {
+    let needle = b"type=\"class\"";
+    bytes.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Rewrite every class property of a Tiled XML document as primitive properties that tiled can
+/// parse, see the [module](self) documentation.
+pub fn flatten_class_properties(bytes: &[u8]) -> Result<Vec<u8>, ClassPropertiesError> {
+    let mut output = Vec::new();
+    let mut writer = EventWriter::new(&mut output);
+
+    // The names of the class properties we are inside of, and for each open property element
+    // whether it is a class.
+    let mut class_path: Vec<String> = vec![];
+    let mut open_properties: Vec<bool> = vec![];
+
+    for event in EventReader::new(bytes) {
+        let event = event?;
+
+        match &event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } if name.local_name == "property" => {
+                let property_name = attribute(attributes, "name").unwrap_or_default();
+                let path = class_path
+                    .iter()
+                    .map(String::as_str)
+                    .chain([property_name])
+                    .collect::<Vec<_>>()
+                    .join(".");
+
+                if attribute(attributes, "type") == Some("class") {
+                    let class = attribute(attributes, "propertytype").unwrap_or_default();
+                    writer.write(
+                        xml::writer::XmlEvent::start_element("property")
+                            .attr("name", &format!("{CLASS_PREFIX}{path}"))
+                            .attr("value", class),
+                    )?;
+                    writer.write(xml::writer::XmlEvent::end_element())?;
+
+                    class_path.push(property_name.to_string());
+                    open_properties.push(true);
+                    continue;
+                }
+
+                open_properties.push(false);
+
+                if !class_path.is_empty() {
+                    let member_name = format!("{MEMBER_PREFIX}{path}");
+                    let mut element = xml::writer::XmlEvent::start_element("property");
+                    for attr in attributes {
+                        element = match attr.name.local_name.as_str() {
+                            "name" => element.attr("name", &member_name),
+                            _ => element.attr(attr.name.borrow(), &attr.value),
+                        };
+                    }
+                    writer.write(element)?;
+                    continue;
+                }
+            }
+            XmlEvent::EndElement { name }
+                if name.local_name == "property" && open_properties.last() == Some(&true) =>
+            {
+                open_properties.pop();
+                class_path.pop();
+                continue;
+            }
+            XmlEvent::EndElement { name } if name.local_name == "property" => {
+                open_properties.pop();
+            }
+            // The members of a class are written straight into the enclosing properties element.
+            XmlEvent::StartElement { name, .. } | XmlEvent::EndElement { name }
+                if name.local_name == "properties" && !class_path.is_empty() =>
+            {
+                continue;
+            }
+            _ => (),
+        }
+
+        if let Some(event) = event.as_writer_event() {
+            writer.write(event)?;
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::tiled_map::BytesResourceReader;
+
+    /// Flatten the class properties of a map with the given properties, and read them back the
+    /// way the loader does.
+    fn round_trip(properties: &str) -> TiledProperties {
+        let tmx = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="1" height="1" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+{properties}
+ </properties>
+</map>
+"#
+        );
+
+        assert!(has_class_properties(tmx.as_bytes()));
+        let xml = flatten_class_properties(tmx.as_bytes()).unwrap();
+        assert!(!has_class_properties(&xml));
+
+        let path = PathBuf::from("map.tmx");
+        let map = tiled::Loader::with_cache_and_reader(
+            tiled::DefaultResourceCache::new(),
+            BytesResourceReader {
+                files: HashMap::from([(path.clone(), xml.into())]),
+            },
+        )
+        .load_tmx_map(path)
+        .unwrap();
+
+        TiledProperties::from(&map.properties)
+    }
+
+    fn class(class: &str, properties: &[(&str, TiledPropertyValue)]) -> TiledPropertyValue {
+        TiledPropertyValue::Class {
+            class: class.to_string(),
+            properties: TiledProperties(
+                properties
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn class_properties_round_trip() {
+        let properties = round_trip(
+            r##"  <property name="speed" type="float" value="1.5"/>
+  <property name="stats" type="class" propertytype="Stats">
+   <properties>
+    <property name="health" type="int" value="10"/>
+    <property name="tint" type="color" value="#ff0000ff"/>
+    <property name="name" value="Guard"/>
+   </properties>
+  </property>"##,
+        );
+
+        assert_eq!(properties.get_float("speed"), Some(1.5));
+        assert_eq!(
+            properties.get("stats"),
+            Some(&class(
+                "Stats",
+                &[
+                    ("health", TiledPropertyValue::Int(10)),
+                    (
+                        "tint",
+                        TiledPropertyValue::Color(Color::rgba_u8(0, 0, 255, 255))
+                    ),
+                    ("name", TiledPropertyValue::String("Guard".to_string())),
+                ]
+            ))
+        );
+        assert_eq!(properties.0.len(), 2);
+    }
+
+    #[test]
+    fn nested_class_properties_round_trip() {
+        let properties = round_trip(
+            r#"  <property name="loot" type="class" propertytype="Loot">
+   <properties>
+    <property name="count" type="int" value="3"/>
+    <property name="item" type="class" propertytype="Item">
+     <properties>
+      <property name="rare" type="bool" value="true"/>
+     </properties>
+    </property>
+   </properties>
+  </property>
+  <property name="empty" type="class" propertytype="Item"/>"#,
+        );
+
+        assert_eq!(
+            properties.get("loot"),
+            Some(&class(
+                "Loot",
+                &[
+                    ("count", TiledPropertyValue::Int(3)),
+                    (
+                        "item",
+                        class("Item", &[("rare", TiledPropertyValue::Bool(true))])
+                    ),
+                ]
+            ))
+        );
+        assert_eq!(properties.get("empty"), Some(&class("Item", &[])));
+    }
+
+    #[test]
+    fn dotted_property_names_round_trip() {
+        let properties = round_trip(
+            r#"  <property name="a.b" value="plain"/>
+  <property name="loot.table" type="class" propertytype="Loot">
+   <properties>
+    <property name="drop.rate" type="float" value="0.25"/>
+    <property name="inner.item" type="class" propertytype="Item">
+     <properties>
+      <property name="x.y" type="int" value="7"/>
+     </properties>
+    </property>
+   </properties>
+  </property>"#,
+        );
+
+        assert_eq!(properties.get_string("a.b"), Some("plain"));
+        assert_eq!(
+            properties.get("loot.table"),
+            Some(&class(
+                "Loot",
+                &[
+                    ("drop.rate", TiledPropertyValue::Float(0.25)),
+                    (
+                        "inner.item",
+                        class("Item", &[("x.y", TiledPropertyValue::Int(7))])
+                    ),
+                ]
+            ))
+        );
+        assert_eq!(properties.0.len(), 2);
+    }
+
+    #[test]
+    fn getters_only_read_properties_of_their_type() {
+        let properties = round_trip(
+            r##"  <property name="bool" type="bool" value="true"/>
+  <property name="int" type="int" value="-3"/>
+  <property name="float" type="float" value="0.5"/>
+  <property name="string" value="text"/>
+  <property name="file" type="file" value="sounds/door.ogg"/>
+  <property name="color" type="color" value="#8000ff00"/>
+  <property name="object" type="object" value="12"/>
+  <property name="unset object" type="object" value="0"/>
+  <property name="class" type="class" propertytype="Item">
+   <properties>
+    <property name="rare" type="bool" value="true"/>
+   </properties>
+  </property>"##,
+        );
+
+        assert_eq!(properties.get_bool("bool"), Some(true));
+        assert_eq!(properties.get_bool("int"), None);
+
+        assert_eq!(properties.get_int("int"), Some(-3));
+        assert_eq!(properties.get_int("float"), None);
+
+        assert_eq!(properties.get_float("float"), Some(0.5));
+        assert_eq!(properties.get_float("int"), None);
+
+        assert_eq!(properties.get_string("string"), Some("text"));
+        assert_eq!(properties.get_string("file"), Some("sounds/door.ogg"));
+        assert_eq!(properties.get_string("color"), None);
+
+        assert_eq!(
+            properties.get_color("color"),
+            Some(Color::rgba_u8(0, 255, 0, 128))
+        );
+        assert_eq!(properties.get_color("string"), None);
+
+        assert_eq!(properties.get_object("object"), Some(12));
+        assert_eq!(properties.get_object("unset object"), None);
+        assert_eq!(properties.get_object("int"), None);
+
+        assert_eq!(
+            properties
+                .get_class("class")
+                .and_then(|item| item.get_bool("rare")),
+            Some(true)
+        );
+        assert_eq!(properties.get_class("bool"), None);
+
+        assert_eq!(properties.get("missing"), None);
+        assert_eq!(properties.get_bool("missing"), None);
+        assert_eq!(properties.get_string("missing"), None);
+    }
+
+    #[test]
+    fn documents_without_class_properties_are_left_alone() {
+        let tmx = br#"<map><properties><property name="speed" type="float" value="1.5"/></properties></map>"#;
+
+        assert!(!has_class_properties(tmx));
+    }
+}
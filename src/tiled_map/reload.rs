@@ -0,0 +1,237 @@
+//! Hot reloading of maps. When a map asset is modified on disk, the content of every map entity
+//! using it is despawned and spawned again from the new asset. Components registered with
+//! [TiledReloadAppExt::preserve_on_tiled_reload] are carried over to the respawned objects,
+//! matched by their Tiled object ID.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    log,
+    prelude::{
+        Added, AnyOf, App, AssetEvent, Commands, Component, DespawnRecursiveExt, Entity,
+        EventReader, EventWriter, Handle, HierarchyQueryExt, IntoSystemConfigs, Or, Parent, Query,
+        ResMut, Resource, Update, With,
+    },
+};
+
+use super::{TiledMap, TiledMapEvent, TiledMapState, TiledMapSystem, TiledObject, TiledShape};
+
+pub trait TiledReloadAppExt {
+    /// Keep the `T` component of spawned objects when their map is hot reloaded. The component is
+    /// copied onto the respawned object with the same Tiled object ID.
+    fn preserve_on_tiled_reload<T: Component + Clone>(&mut self) -> &mut Self;
+}
+
+impl TiledReloadAppExt for App {
+    fn preserve_on_tiled_reload<T: Component + Clone>(&mut self) -> &mut Self {
+        self.init_resource::<PreservedComponents<T>>().add_systems(
+            Update,
+            (
+                preserve_components::<T>.in_set(TiledMapSystem::Reload),
+                restore_components::<T>.in_set(TiledMapSystem::Restore),
+            ),
+        )
+    }
+}
+
+/// Components taken from the objects of reloading maps, keyed by map entity and Tiled object ID
+#[derive(Resource)]
+struct PreservedComponents<T: Component + Clone>(HashMap<(Entity, u32), T>);
+
+impl<T: Component + Clone> Default for PreservedComponents<T> {
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
+/// Despawn the content of every map entity whose asset was modified, so that it is spawned again
+/// once the new asset is ready.
+pub fn reload_modified_maps(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<TiledMap>>,
+    mut map_query: Query<(Entity, &Handle<TiledMap>, &mut TiledMapState)>,
+    mut map_events: EventWriter<TiledMapEvent>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+
+        for (map_entity, map_handle, mut state) in map_query.iter_mut() {
+            // Maps still loading have nothing to despawn, and will spawn from the new asset.
+            if map_handle.id() != *id || *state != TiledMapState::Spawned {
+                continue;
+            }
+
+            log::info!("Reloading map content for {:?}", map_entity);
+
+            commands.entity(map_entity).despawn_descendants();
+            *state = TiledMapState::Loading;
+
+            map_events.send(TiledMapEvent::Despawned {
+                entity: map_entity,
+                handle: map_handle.clone(),
+            });
+        }
+    }
+}
+
+/// Try again to spawn the maps that failed to load, once their asset has loaded, e.g. after the
+/// error in the file was fixed.
+pub fn retry_failed_maps(
+    mut events: EventReader<AssetEvent<TiledMap>>,
+    mut map_query: Query<(Entity, &Handle<TiledMap>, &mut TiledMapState)>,
+) {
+    for event in events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::LoadedWithDependencies { id }) = event else {
+            continue;
+        };
+
+        for (map_entity, map_handle, mut state) in map_query.iter_mut() {
+            if map_handle.id() != *id || *state != TiledMapState::Failed {
+                continue;
+            }
+
+            log::info!("Retrying failed map {:?}", map_entity);
+
+            *state = TiledMapState::Loading;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn preserve_components<T: Component + Clone>(
+    mut events: EventReader<AssetEvent<TiledMap>>,
+    mut preserved: ResMut<PreservedComponents<T>>,
+    map_query: Query<(Entity, &Handle<TiledMap>)>,
+    object_query: Query<(Entity, AnyOf<(&TiledObject, &TiledShape)>, &T)>,
+    parent_query: Query<&Parent>,
+) {
+    let modified: HashSet<_> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    if modified.is_empty() {
+        return;
+    }
+
+    for (entity, (tiled_object, tiled_shape), component) in object_query.iter() {
+        let Some(map_entity) = parent_query.iter_ancestors(entity).find(|ancestor| {
+            map_query
+                .get(*ancestor)
+                .is_ok_and(|(_, map_handle)| modified.contains(&map_handle.id()))
+        }) else {
+            continue;
+        };
+
+        let object_id = match (tiled_object, tiled_shape) {
+            (Some(tiled_object), _) => tiled_object.id,
+            (_, Some(tiled_shape)) => tiled_shape.id,
+            _ => continue,
+        };
+
+        preserved
+            .0
+            .insert((map_entity, object_id), component.clone());
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn restore_components<T: Component + Clone>(
+    mut commands: Commands,
+    mut preserved: ResMut<PreservedComponents<T>>,
+    map_query: Query<Entity, With<Handle<TiledMap>>>,
+    object_query: Query<
+        (Entity, AnyOf<(&TiledObject, &TiledShape)>),
+        Or<(Added<TiledObject>, Added<TiledShape>)>,
+    >,
+    parent_query: Query<&Parent>,
+) {
+    if preserved.0.is_empty() {
+        return;
+    }
+
+    let mut respawned_maps = HashSet::new();
+
+    for (entity, (tiled_object, tiled_shape)) in object_query.iter() {
+        let Some(map_entity) = parent_query
+            .iter_ancestors(entity)
+            .find(|ancestor| map_query.contains(*ancestor))
+        else {
+            continue;
+        };
+
+        respawned_maps.insert(map_entity);
+
+        let object_id = match (tiled_object, tiled_shape) {
+            (Some(tiled_object), _) => tiled_object.id,
+            (_, Some(tiled_shape)) => tiled_shape.id,
+            _ => continue,
+        };
+
+        if let Some(component) = preserved.0.remove(&(map_entity, object_id)) {
+            commands.entity(entity).insert(component);
+        }
+    }
+
+    // Anything left over for a respawned map belonged to an object that no longer exists.
+    preserved
+        .0
+        .retain(|(map_entity, _), _| !respawned_maps.contains(map_entity));
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Assets;
+
+    use super::*;
+    use crate::tiled_map::{
+        tests::{app_with_map, map_from_tmx},
+        TiledLoaderSettings,
+    };
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <objectgroup id="1" name="Objects"/>
+</map>
+"#;
+
+    #[test]
+    fn failed_maps_are_spawned_once_their_asset_loads() {
+        let (mut app, _, map_entity) = app_with_map(TMX, TiledLoaderSettings::default());
+        app.world
+            .entity_mut(map_entity)
+            .insert(TiledMapState::Failed);
+
+        let other_handle = app
+            .world
+            .resource::<Assets<TiledMap>>()
+            .get_handle_provider()
+            .reserve_handle()
+            .typed::<TiledMap>();
+        let other_entity = app
+            .world
+            .spawn((other_handle.clone(), TiledMapState::Failed))
+            .id();
+
+        app.update();
+        app.update();
+
+        let state = |app: &App, entity| *app.world.get::<TiledMapState>(entity).unwrap();
+        assert_eq!(state(&app, map_entity), TiledMapState::Spawned);
+        assert_eq!(state(&app, other_entity), TiledMapState::Failed);
+
+        app.world.resource_mut::<Assets<TiledMap>>().insert(
+            &other_handle,
+            map_from_tmx(TMX, TiledLoaderSettings::default()),
+        );
+        app.update();
+        app.update();
+
+        assert_eq!(state(&app, other_entity), TiledMapState::Spawned);
+    }
+}
@@ -0,0 +1,153 @@
+//! The tint and opacity of layers. Every layer entity, groups included, holds its own, and the
+//! content of a layer is drawn with them combined with those of the groups it is in, the way Tiled
+//! draws it. Changing them at runtime recolours the layer's content.
+
+use bevy::{
+    ecs::change_detection::DetectChanges,
+    hierarchy::{Children, HierarchyQueryExt, Parent},
+    math::Vec4,
+    prelude::{Component, Entity, Query, Ref, ReflectComponent, Without},
+    reflect::Reflect,
+    render::color::Color,
+    sprite::{Sprite, TextureAtlasSprite},
+    utils::Instant,
+};
+use bevy_simple_tilemap::TileMap;
+
+use super::{TiledAnimatedTiles, TiledCollideable, TiledShape};
+
+/// TiledLayerStyle holds the tint colour and opacity of a layer
+#[derive(Reflect, Component, Debug, Clone, Copy)]
+#[reflect(Component)]
+pub struct TiledLayerStyle {
+    /// The colour the layer's content is multiplied with
+    pub tint: Color,
+    /// The opacity of the layer's content, from 0 for transparent to 1 for opaque
+    pub opacity: f32,
+}
+
+impl Default for TiledLayerStyle {
+    fn default() -> Self {
+        Self {
+            tint: Color::WHITE,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl From<&tiled::Layer<'_>> for TiledLayerStyle {
+    fn from(layer: &tiled::Layer) -> Self {
+        let tint = match layer.tint_color {
+            Some(tint) => Color::rgba_u8(tint.red, tint.green, tint.blue, tint.alpha),
+            None => Color::WHITE,
+        };
+
+        Self {
+            tint,
+            opacity: layer.opacity,
+        }
+    }
+}
+
+impl TiledLayerStyle {
+    /// The colour of the layer's content, given the colour its groups draw it with
+    pub fn apply(&self, color: Color) -> Color {
+        let color = Vec4::from(color.as_rgba_f32()) * Vec4::from(self.tint.as_rgba_f32());
+        let mut color = Color::from(color);
+        color.set_a(color.a() * self.opacity);
+        color
+    }
+}
+
+/// Recolour the content of every layer whose style, or the style of one of its groups, changed
+/// since it was spawned. Collideables and shapes keep their debug colours.
+#[allow(clippy::type_complexity)]
+pub fn restyle_layers(
+    layer_query: Query<(Entity, Ref<TiledLayerStyle>, Option<&Children>)>,
+    parent_query: Query<&Parent>,
+    mut content_query: Query<
+        (
+            Option<&mut TileMap>,
+            Option<&mut TiledAnimatedTiles>,
+            Option<&mut TextureAtlasSprite>,
+            Option<&mut Sprite>,
+            Option<&Children>,
+        ),
+        (
+            Without<TiledLayerStyle>,
+            Without<TiledCollideable>,
+            Without<TiledShape>,
+        ),
+    >,
+) {
+    for (entity, _, children) in layer_query.iter() {
+        let Some(children) = children else {
+            continue;
+        };
+
+        // The layer and its groups, from the layer up to the outermost group
+        let mut styles = vec![];
+        let mut changed = false;
+
+        for ancestor in std::iter::once(entity).chain(parent_query.iter_ancestors(entity)) {
+            let Ok((_, style, _)) = layer_query.get(ancestor) else {
+                break;
+            };
+
+            changed |= style.is_changed() && !style.is_added();
+            styles.push(*style);
+        }
+
+        if !changed {
+            continue;
+        }
+
+        let color = styles
+            .iter()
+            .rev()
+            .fold(Color::WHITE, |color, style| style.apply(color));
+
+        // The content of a layer is never another layer, so the walk stops at the layers of a
+        // group, which are recoloured on their own.
+        let mut content: Vec<Entity> = children.iter().copied().collect();
+
+        while let Some(entity) = content.pop() {
+            let Ok((tilemap, animated_tiles, atlas_sprite, sprite, children)) =
+                content_query.get_mut(entity)
+            else {
+                continue;
+            };
+
+            if let Some(mut tilemap) = tilemap {
+                recolor_tilemap(&mut tilemap, color);
+            }
+
+            if let Some(mut animated_tiles) = animated_tiles {
+                animated_tiles.set_color(color);
+            }
+
+            if let Some(mut atlas_sprite) = atlas_sprite {
+                atlas_sprite.color = color;
+            }
+
+            if let Some(mut sprite) = sprite {
+                sprite.color = color;
+            }
+
+            if let Some(children) = children {
+                content.extend(children.iter().copied());
+            }
+        }
+    }
+}
+
+/// Set the colour of every tile already placed in a tilemap, and mark its chunks for remeshing
+fn recolor_tilemap(tilemap: &mut TileMap, color: Color) {
+    for chunk in tilemap.chunks.values_mut() {
+        for tile in chunk.tiles.iter_mut().flatten() {
+            tile.color = color;
+        }
+
+        chunk.last_change_at = Instant::now();
+    }
+}
@@ -0,0 +1,65 @@
+//! Y-sorting. Entities are drawn by how far down they are, so that characters and props sharing an
+//! object layer overlap the way they would from a top-down view: the player walks behind a fountain
+//! while above it, and in front of it while below it.
+//!
+//! The objects of a layer are y-sorted when the layer's `ysort` property is set, or for every
+//! object layer of a map when its loader settings ask for it. Game entities are y-sorted by giving
+//! them a [YSort] of their own, usually the one of the layer they share.
+
+use bevy::{
+    prelude::{Changed, Component, Or, Query, ReflectComponent, Transform},
+    reflect::Reflect,
+};
+
+/// The bool layer property that turns y-sorting on or off for the objects of a layer
+pub const YSORT_PROPERTY: &str = "ysort";
+
+/// YSort sets the z of an entity from its y every frame, both in the space of its parent. The
+/// lower down the range an entity is, the further in front it is drawn.
+#[derive(Reflect, Component, Debug, Clone, Copy, Default)]
+#[reflect(Component)]
+pub struct YSort {
+    /// The z of an entity at the top of the range
+    pub z: f32,
+    /// How much further in front an entity at the bottom of the range is drawn
+    pub depth: f32,
+    /// The y of the top of the range
+    pub top: f32,
+    /// The y of the bottom of the range
+    pub bottom: f32,
+}
+
+impl YSort {
+    pub fn new(z: f32, depth: f32, top: f32, bottom: f32) -> Self {
+        Self {
+            z,
+            depth,
+            top,
+            bottom,
+        }
+    }
+
+    /// The z of an entity at `y`. Entities beyond the range are drawn at its ends.
+    pub fn z_at(&self, y: f32) -> f32 {
+        if self.top <= self.bottom {
+            return self.z;
+        }
+
+        let fraction = ((self.top - y) / (self.top - self.bottom)).clamp(0.0, 1.0);
+        self.z + fraction * self.depth
+    }
+}
+
+/// Set the z of every y-sorted entity that moved.
+#[allow(clippy::type_complexity)]
+pub fn y_sort(
+    mut query: Query<(&YSort, &mut Transform), Or<(Changed<YSort>, Changed<Transform>)>>,
+) {
+    for (y_sort, mut transform) in query.iter_mut() {
+        let z = y_sort.z_at(transform.translation.y);
+
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    }
+}